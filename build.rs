@@ -2,15 +2,28 @@ use std::fs;
 use std::io::{BufWriter, Write};
 use std::path::Path;
 use std::{io::BufReader, fs::File};
-use std::collections::HashMap;
-use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use serde::Deserialize;
+
+// Shared with the simulator so the data is validated the same way at build time
+#[allow(dead_code)]
+#[path = "src/parse.rs"]
+mod parse;
+#[allow(dead_code)]
+#[path = "src/cache.rs"]
+mod cache;
 
 #[derive(Deserialize, Debug, Clone)]
 struct Gom {
     // associations: HashMap<u64, String>,
     // classes: HashMap<u64, String>,
     enums: HashMap<u64, String>,
-    // fields: HashMap<u64, String>,
+    fields: HashMap<u64, String>,
+}
+
+fn read_gom() -> Gom {
+    let gom_file = File::open(format!("data/{}", cache::GOM_FILE)).expect("GOM.json should exist, see rebuild in the README");
+    serde_json::from_reader(BufReader::new(gom_file)).expect("json should be good")
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -20,71 +33,167 @@ struct ClientGom {
     // classes: // Probably not necessary
 }
 
-fn create_enums(out_dir: &Path) {
+// Strict and reserved keywords, none of which can be a variant name
+static KEYWORDS: [&str; 51] = [
+    "as", "break", "const", "continue", "crate", "else", "enum", "extern", "false", "fn", "for", "if",
+    "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub", "ref", "return", "self", "Self",
+    "static", "struct", "super", "trait", "true", "type", "unsafe", "use", "where", "while", "async",
+    "await", "dyn", "abstract", "become", "box", "do", "final", "macro", "override", "priv", "typeof",
+    "unsized", "virtual", "yield", "try",
+];
+
+/// Turns a GOM name into a valid identifier, e.g. `move` -> `move_` and `1h` -> `_1h`
+fn sanitize_ident(name: &str) -> String {
+    let mut ident: String = name.chars()
+                                .map(|c| if c.is_ascii_alphanumeric() || c == '_' { c } else { '_' })
+                                .collect();
+    if ident.is_empty() || ident == "_" {
+        ident = "_empty".to_string();
+    } else if ident.starts_with(|c: char| c.is_ascii_digit()) {
+        ident.insert(0, '_');
+    } else if KEYWORDS.contains(&ident.as_str()) {
+        ident.push('_');
+    }
+    ident
+}
+
+/// Adds a number to `ident` until it doesn't collide with a used name, ignoring case
+fn dedupe_ident(ident: String, used: &mut HashSet<String>) -> String {
+    let mut unique = ident.clone();
+    let mut n = 2;
+    while !used.insert(unique.to_lowercase()) {
+        unique = format!("{ident}_{n}");
+        n += 1;
+    }
+    unique
+}
+
+fn create_enums(out_dir: &Path, gom: &Gom) {
     let enums_file = File::create(out_dir.join("enums.rs")).expect("should be able to create file");
     let mut enums = BufWriter::new(enums_file);
     let type_reg_file = File::create(out_dir.join("type_registration.rs")).expect("should be able to create file");
     let mut type_reg = BufWriter::new(type_reg_file);
+    // Everything that didn't make it into types as is
+    let mut report = vec![];
 
     writeln!(&mut type_reg, "app").expect("should write");
 
-    let client_gom_file = File::open("data/clientGom.json").expect("clientGom.json should exist");
+    let client_gom_file = File::open(format!("data/{}", cache::CLIENT_GOM_FILE)).expect("clientGom.json should exist, see rebuild in the README");
     let client_gom: ClientGom = serde_json::from_reader(BufReader::new(client_gom_file)).expect("json should be good");
-    let gom_file = File::open("data/GOM.json").expect("GOM.json should exist");
-    let gom: Gom = serde_json::from_reader(BufReader::new(gom_file)).expect("json should be good");
-
-    for (id, name) in gom.enums {
-        let variants = match client_gom.enums.get(&id) {
-            None => continue,
-            Some(v) => v,
+    let mut gom_enums: Vec<_> = gom.enums.iter().collect();
+    gom_enums.sort();
+    let mut type_names = HashSet::new();
+    // GOM enum name -> variant names, for enums that are only looked up by name
+    let mut variant_names = vec![];
+    let mut gom_names = HashSet::new();
+    for (id, gom_name) in gom_enums {
+        let Some(variants) = client_gom.enums.get(id) else {
+            report.push(format!("skipped enum {gom_name} ({id}): no variants in clientGom.json"));
+            continue;
         };
-        if variants.iter().find(|var| var == &"move").is_some() {
-            // TODO: Solve this problem (can't have reserved words as enum variant)
+        if gom_names.insert(gom_name) {
+            variant_names.push(format!("        {gom_name:?} => Some(&{variants:?}),"));
+        }
+        let name = sanitize_ident(gom_name);
+        if !type_names.insert(name.clone()) {
+            report.push(format!("skipped enum {gom_name} ({id}): {name} already exists"));
             continue;
         }
-        if variants.len() == 0 {
+        if name != *gom_name {
+            report.push(format!("renamed enum {gom_name} to {name}"));
+        }
+        if variants.is_empty() {
+            // Can't have a repr, but still converts (never successfully) like the other enums
+            report.push(format!("enum {name} has no variants"));
+            writeln!(&mut enums,
+                     "#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, strum::Display, strum::EnumString, Component, Reflect)]\npub enum {name} {{}}\ngom_enum!({name}, []);").expect("should write");
+            writeln!(&mut type_reg, ".register_type::<types::{name}>()").expect("should write");
             continue;
         }
+        let mut used = HashSet::new();
+        let idents: Vec<_> = variants.iter()
+                                     .map(|var| {
+                                         let ident = dedupe_ident(sanitize_ident(var), &mut used);
+                                         if ident != *var {
+                                             report.push(format!("renamed {name}::{var:?} to {ident}"));
+                                         }
+                                         ident
+                                     })
+                                     .collect();
+        // Nodes store enum values as 1 based indices into the variant list
+        let definitions: Vec<_> = variants.iter()
+                                          .zip(&idents)
+                                          .enumerate()
+                                          .map(|(i, (var, ident))| if ident == var {
+                                              format!("{ident} = {}", i + 1)
+                                          } else {
+                                              format!("#[strum(serialize = {var:?})]\n    {ident} = {}", i + 1)
+                                          })
+                                          .collect();
         writeln!(&mut enums,
-                 "#[derive(Debug, strum::Display, strum::EnumString, Component, Reflect)]\npub enum {name} {{\n    {},\n}}",
-                 variants.join(",\n    ")).expect("should write");
+                 "#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, strum::Display, strum::EnumString, Component, Reflect)]\n#[repr(u64)]\npub enum {name} {{\n    {},\n}}\ngom_enum!({name}, [{}]);",
+                 definitions.join(",\n    "), idents.join(", ")).expect("should write");
         writeln!(&mut type_reg, ".register_type::<types::{name}>()").expect("should write");
     }
-    // TODO: Add id <-> enum perhaps?
-}
 
-#[derive(Deserialize, Debug)]
-pub struct Node {
-    pub id: String,
-    pub fqn: String,
-    pub path: String,
-    #[serde(rename = "fileName")]
-    pub file_name: String,
+    writeln!(&mut enums,
+             "pub fn gom_variants(enum_name: &str) -> Option<&'static [&'static str]> {{\n    match enum_name {{\n{}\n        _ => None,\n    }}\n}}",
+             variant_names.join("\n")).expect("should write");
+
+    let report_path = out_dir.join("enum_report.txt");
+    fs::write(&report_path, report.join("\n")).expect("should be able to write report");
+    if !report.is_empty() {
+        println!("cargo:warning={} GOM enums or variants were renamed or skipped, see {}", report.len(), report_path.display());
+    }
 }
 
-#[derive(Deserialize, Serialize, Debug, Clone)]
-pub struct CodeClass(pub Vec<serde_json::Value>);
+// Field id <-> name lookup, used by gom.rs
+fn create_fields(out_dir: &Path, gom: &Gom) {
+    let fields_file = File::create(out_dir.join("fields.rs")).expect("should be able to create file");
+    let mut fields = BufWriter::new(fields_file);
+
+    let mut gom_fields: Vec<_> = gom.fields.iter().collect();
+    gom_fields.sort();
 
-#[derive(Deserialize, Debug)]
-pub struct NodeObjPair {
-    pub node: Node,
-    pub obj: CodeClass,
+    // Every id has a name, but a name with several ids can only map back to one of them
+    writeln!(&mut fields, "pub fn field_name(id: u64) -> Option<&'static str> {{\n    match id {{").expect("should write");
+    for (id, name) in &gom_fields {
+        writeln!(&mut fields, "        {id} => Some({name:?}),").expect("should write");
+    }
+    writeln!(&mut fields, "        _ => None,\n    }}\n}}\n").expect("should write");
+
+    let mut seen = HashMap::new();
+    let by_name: Vec<_> = gom_fields.iter().filter(|(id, name)| match seen.insert(*name, *id) {
+        None => true,
+        Some(first) => {
+            println!("cargo:warning=GOM field {name} has multiple ids ({first}, {id}), only the first one is used by name");
+            false
+        },
+    }).collect();
+    writeln!(&mut fields, "pub fn field_id(name: &str) -> Option<u64> {{\n    match name {{").expect("should write");
+    for (id, name) in by_name {
+        writeln!(&mut fields, "        {name:?} => Some({id}),").expect("should write");
+    }
+    writeln!(&mut fields, "        _ => None,\n    }}\n}}").expect("should write");
 }
 
-fn create_abilities(out_dir: &Path) {
-    let abl_file = File::open("data/abl.json").expect("abl.json should exist");
-    let abl: Vec<NodeObjPair> = serde_json::from_reader(BufReader::new(abl_file)).expect("json should be good");
-
-    for pair in abl {
-        let mut path_str = pair.node.fqn.replace(".", "/");
-        path_str.push_str(".json");
-        let path = out_dir.join(path_str);
-        let parent = path.parent().expect("should have parent");
-        fs::create_dir_all(parent).expect("can create directories");
-        let mut file = File::create(path).expect("should be able to create file");
-        let json_string = serde_json::to_string(&pair.obj).expect("should get json");
-        file.write_all(json_string.as_bytes()).expect("should be able to write");
+fn create_data_cache(out_dir: &Path) {
+    let mut data = cache::CacheData::default();
+    for prefix in cache::NODE_PREFIXES {
+        let file_name = format!("data/{prefix}.json");
+        let Ok(file) = File::open(&file_name) else {
+            println!("cargo:warning={file_name} not found, no {prefix} nodes in the data cache");
+            continue;
+        };
+        let nodes: Vec<parse::NodeObjPair> = serde_json::from_reader(BufReader::new(file)).unwrap_or_else(|e| panic!("{file_name}: {e}"));
+        data.nodes.extend(nodes);
+    }
+    let file_name = format!("data/{}", cache::STRINGS_FILE);
+    match File::open(&file_name) {
+        Ok(file) => data.strings = serde_json::from_reader(BufReader::new(file)).unwrap_or_else(|e| panic!("{file_name}: {e}")),
+        Err(_) => println!("cargo:warning={file_name} not found, no strings in the data cache"),
     }
+    fs::write(out_dir.join(cache::CACHE_FILE), cache::encode(&data)).expect("should be able to write cache");
 }
 
 fn main() {
@@ -92,6 +201,8 @@ fn main() {
     println!("cargo:rerun-if-changed=data/");
     let out_dir_str = std::env::var_os("OUT_DIR").unwrap();
     let out_dir = Path::new(&out_dir_str);
-    create_enums(out_dir);
-    create_abilities(out_dir);
+    let gom = read_gom();
+    create_enums(out_dir, &gom);
+    create_fields(out_dir, &gom);
+    create_data_cache(out_dir);
 }
@@ -0,0 +1,619 @@
+#![allow(dead_code)]
+
+// Runs effect actions against entities. Effects are applied with `ApplyEffect` events,
+// which one exclusive system handles, so effects called by other effects happen right away.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+use bevy::prelude::*;
+use crate::effect::{param_key, ActionName, EffAct, Effect, EffectSlot, GomName, Params, SubEffect};
+use crate::game_data::GameData;
+use crate::gom::FieldError;
+use crate::parse::{Id, NodeRef};
+use crate::rng::{CombatRng, RollKind};
+use crate::stats::{Stat, Stats};
+use crate::talent::Talents;
+use crate::timeline::SimTime;
+use crate::types::variant_name;
+use crate::utils::constants::BASE_ACCURACY;
+use crate::utils::formulas::{armor_reduction, tooltip_damage};
+
+static PARAM_COEFFICIENT: &str = "Coefficient";
+static PARAM_SHP_MIN: &str = "StandardHealthPercentMin";
+static PARAM_SHP_MAX: &str = "StandardHealthPercentMax";
+static PARAM_AMP: &str = "AmountModifierPercent";
+static PARAM_DAMAGE_TYPE: &str = "DamageType";
+static PARAM_STAT: &str = "Stat";
+static PARAM_VALUE: &str = "Value";
+static PARAM_EFFECT: &str = "Effect";
+static PARAM_THREAT: &str = "Threat";
+
+/// Effects calling effects deeper than this are assumed to loop
+static MAX_CALL_DEPTH: usize = 8;
+
+#[derive(Debug)]
+pub enum ActionError {
+    Field(FieldError),
+    Unsupported(String),
+    NoEntity(Entity),
+    TooDeep(u64),
+}
+
+impl fmt::Display for ActionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ActionError::Field(e) => write!(f, "{e}"),
+            ActionError::Unsupported(name) => write!(f, "unsupported action {name}"),
+            ActionError::NoEntity(entity) => write!(f, "entity {entity:?} doesn't exist"),
+            ActionError::TooDeep(id) => write!(f, "effect {id} is nested more than {MAX_CALL_DEPTH} deep"),
+        }
+    }
+}
+
+impl std::error::Error for ActionError {}
+
+impl From<FieldError> for ActionError {
+    fn from(value: FieldError) -> Self {
+        ActionError::Field(value)
+    }
+}
+
+/// Bonus damage of a caster, from mastery and power
+#[derive(Component, Debug, Default)]
+pub struct BonusDamage(pub f64);
+
+/// Crit chance and surge of a caster as fractions. A crit does `1 + surge` times the damage
+#[derive(Component, Debug, Default, Clone, Copy, PartialEq)]
+pub struct Crit {
+    pub chance: f64,
+    pub surge: f64,
+}
+
+/// Accuracy of a caster as a fraction, 1.0 is 100%. Without one a caster has [`BASE_ACCURACY`]
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct Accuracy(pub f64);
+
+/// Chance of a target to avoid an attack, lowered by the attacker's accuracy over 100%
+#[derive(Component, Debug, Default, Clone, Copy, PartialEq)]
+pub struct Defense(pub f64);
+
+/// Armor of a target, after debuffs. It only mitigates kinetic and energy damage
+#[derive(Component, Debug, Default, Clone, Copy, PartialEq)]
+pub struct Armor(pub f64);
+
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct WeaponDamage {
+    pub min: f64,
+    pub max: f64,
+}
+
+/// Total damage taken
+#[derive(Component, Debug, Default, PartialEq)]
+pub struct DamageTaken(pub f64);
+
+/// Changes from effects to stats `Stats` doesn't have, by stat id
+#[derive(Component, Debug, Default, PartialEq)]
+pub struct StatModifiers(pub HashMap<u64, f64>);
+
+/// An effect with a duration, on the entity it was applied to
+#[derive(Debug, Clone, PartialEq)]
+pub struct ActiveEffect {
+    pub effect: u64,
+    pub caster: Entity,
+    pub slot: EffectSlot,
+    pub stacks: u32,
+    pub expires: Duration,
+    /// What its ModifyStat actions added, by stat id, taken back when it ends
+    pub stats: Vec<(u64, f64)>,
+}
+
+impl ActiveEffect {
+    /// Whether applying `effect` from `caster` refreshes this one instead of adding another
+    fn is(&self, effect: &Effect, caster: Entity) -> bool {
+        self.effect == effect.id && (!effect.stack_limit_is_by_caster || self.caster == caster)
+    }
+}
+
+/// Effects with a duration on an entity. Effects without one aren't tracked, what they do lasts
+#[derive(Component, Debug, Default, PartialEq)]
+pub struct ActiveEffects(pub Vec<ActiveEffect>);
+
+/// The effect an action runs for
+#[derive(Debug, Clone, Copy)]
+pub struct Applying<'a> {
+    pub effect: &'a Effect,
+    /// Whether the effect got another stack. A refresh at the stack limit doesn't modify stats again
+    pub stacked: bool,
+    /// How many effects called this one
+    pub depth: usize,
+}
+
+/// Threat of every entity that attacked this one
+#[derive(Component, Debug, Default, PartialEq)]
+pub struct ThreatTable(pub HashMap<Entity, f64>);
+
+#[derive(Event, Debug, Clone)]
+pub struct ApplyEffect {
+    pub caster: Entity,
+    pub target: Entity,
+    /// Node id of the ability, or of the effect for procs, that caused this
+    pub source: u64,
+    pub effect: Effect,
+}
+
+#[derive(Event, Debug, Clone, PartialEq)]
+pub struct DamageDealt {
+    pub caster: Entity,
+    pub target: Entity,
+    pub source: u64,
+    pub amount: f64,
+    pub crit: bool,
+}
+
+/// Sent after all actions of an `ApplyEffect` ran
+#[derive(Event, Debug, Clone)]
+pub struct EffectApplied {
+    pub caster: Entity,
+    pub target: Entity,
+    pub effect: Effect,
+}
+
+/// Damage of one hit. `roll` is where in the SHP and weapon damage ranges it lands, 0.5 is the average
+pub fn hit_damage(coefficient: f64, shp: (f64, f64), bonus_dmg: f64, weapon: Option<(&WeaponDamage, f64)>, roll: f64) -> f64 {
+    let lerp = |min: f64, max: f64| min + (max - min) * roll;
+    let (weapon_dmg, amp) = weapon.map_or((0.0, 0.0), |(weapon, amp)| (lerp(weapon.min, weapon.max), amp));
+    tooltip_damage(weapon_dmg, amp, coefficient, bonus_dmg, lerp(shp.0, shp.1))
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, strum::EnumString)]
+pub enum DamageType {
+    #[default]
+    Kinetic,
+    Energy,
+    Internal,
+    Elemental,
+}
+
+impl GomName for DamageType {
+    const ENUM: &'static str = "effDamageType";
+}
+
+impl DamageType {
+    /// Armor only mitigates kinetic and energy damage
+    pub fn is_armored(self) -> bool {
+        matches!(self, DamageType::Kinetic | DamageType::Energy)
+    }
+}
+
+/// Params of a SpellDamage action
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpellDamage {
+    pub coefficient: f64,
+    /// Standard health percent, min and max
+    pub shp: (f64, f64),
+    /// Amount modifier percent, only scales weapon damage
+    pub amp: f64,
+    /// Kinetic without the param
+    pub damage_type: DamageType,
+}
+
+impl SpellDamage {
+    pub fn from_params(params: &Params) -> Result<Self, FieldError> {
+        let coefficient = f64::from(param(params, PARAM_COEFFICIENT, Params::float)?);
+        let shp_min = f64::from(param_or(params, PARAM_SHP_MIN, Params::float, 0.0)?);
+        let shp_max = f64::from(param_or(params, PARAM_SHP_MAX, Params::float, shp_min as f32)?);
+        let amp = f64::from(param_or(params, PARAM_AMP, Params::float, 0.0)?);
+        let damage_type = match params.id(param_key(PARAM_DAMAGE_TYPE)?) {
+            None => DamageType::default(),
+            Some(value) => variant_name(DamageType::ENUM, value)
+                .and_then(|name| DamageType::from_str(name).ok())
+                .ok_or_else(|| FieldError::invalid(&format!("effParams.{PARAM_DAMAGE_TYPE}"), format!("unknown damage type {value}")))?,
+        };
+        Ok(SpellDamage { coefficient, shp: (shp_min, shp_max), amp, damage_type })
+    }
+
+    pub fn hit(&self, bonus_dmg: f64, weapon: Option<&WeaponDamage>, roll: f64) -> f64 {
+        hit_damage(self.coefficient, self.shp, bonus_dmg, weapon.map(|weapon| (weapon, self.amp)), roll)
+    }
+}
+
+pub fn param<T>(params: &Params, name: &str, get: fn(&Params, u64) -> Option<T>) -> Result<T, FieldError> {
+    get(params, param_key(name)?).ok_or_else(|| FieldError::Missing(format!("effParams.{name}")))
+}
+
+pub fn param_or<T>(params: &Params, name: &str, get: fn(&Params, u64) -> Option<T>, default: T) -> Result<T, FieldError> {
+    Ok(get(params, param_key(name)?).unwrap_or(default))
+}
+
+/// Component of `entity`, added with its default value if it doesn't have one
+fn component_mut<T: Component + Default>(world: &mut World, entity: Entity) -> Result<Mut<'_, T>, ActionError> {
+    let mut entity_mut = world.get_entity_mut(entity).ok_or(ActionError::NoEntity(entity))?;
+    if !entity_mut.contains::<T>() {
+        entity_mut.insert(T::default());
+    }
+    Ok(world.get_mut::<T>(entity).expect("was just inserted"))
+}
+
+/// Adds `value` to a stat of `target`, in `Stats` if it has the stat or `StatModifiers` otherwise
+fn modify_stat(world: &mut World, target: Entity, stat: u64, value: f64) -> Result<(), ActionError> {
+    match (Stat::from_id(stat), world.get_mut::<Stats>(target)) {
+        (Some(stat), Some(mut stats)) => stats.value_mut(stat).bonus += value,
+        _ => *component_mut::<StatModifiers>(world, target)?.0.entry(stat).or_default() += value,
+    }
+    Ok(())
+}
+
+fn now(world: &World) -> Duration {
+    world.get_resource::<SimTime>().map_or(Duration::ZERO, |time| time.0)
+}
+
+/// Takes back the stats `effect` changed on `entity`
+fn end_effect(world: &mut World, entity: Entity, effect: ActiveEffect) -> Result<(), ActionError> {
+    for (stat, value) in effect.stats {
+        modify_stat(world, entity, stat, -value)?;
+    }
+    Ok(())
+}
+
+/// Starts or refreshes a timed `effect` on `target`. Returns whether it got another stack,
+/// which it doesn't once it has `stack_limit` of them
+fn start_effect(world: &mut World, caster: Entity, target: Entity, effect: &Effect) -> Result<bool, ActionError> {
+    let now = now(world);
+    let expires = now + effect.duration;
+    let mut active = component_mut::<ActiveEffects>(world, target)?;
+    let Some(index) = active.0.iter().position(|active| active.is(effect, caster)) else {
+        active.0.push(ActiveEffect { effect: effect.id, caster, slot: effect.slot.clone(), stacks: 1, expires, stats: vec![] });
+        return Ok(true);
+    };
+    // Ran out before expire_effects got to it
+    if active.0[index].expires <= now {
+        let ended = std::mem::replace(&mut active.0[index], ActiveEffect { effect: effect.id, caster, slot: effect.slot.clone(), stacks: 1, expires, stats: vec![] });
+        end_effect(world, target, ended)?;
+        return Ok(true);
+    }
+    let active = &mut active.0[index];
+    active.expires = expires;
+    let stacked = active.stacks < effect.stack_limit.max(1);
+    if stacked {
+        active.stacks += 1;
+    }
+    Ok(stacked)
+}
+
+/// Ends timed effects that ran out, taking back the stats they changed
+pub fn expire_effects(world: &mut World) {
+    let now = now(world);
+    let mut ended = vec![];
+    for (entity, mut active) in world.query::<(Entity, &mut ActiveEffects)>().iter_mut(world) {
+        let (lasting, expired): (Vec<_>, Vec<_>) = std::mem::take(&mut active.0).into_iter().partition(|effect| effect.expires > now);
+        active.0 = lasting;
+        ended.extend(expired.into_iter().map(|effect| (entity, effect)));
+    }
+    for (entity, effect) in ended {
+        let id = effect.effect;
+        if let Err(e) = end_effect(world, entity, effect) {
+            warn!("effect {id}: {e}");
+        }
+    }
+}
+
+pub fn run_action(world: &mut World, caster: Entity, target: Entity, source: u64, action: &EffAct<ActionName>, applying: Applying, problems: &mut Vec<ActionError>) -> Result<(), ActionError> {
+    let params = &action.params;
+    match &action.name {
+        ActionName::SpellDamage => {
+            let spell_damage = SpellDamage::from_params(params)?;
+            let mut crit = world.get::<Crit>(caster).copied().unwrap_or_default();
+            let talents = world.get::<Talents>(caster);
+            let multiplier = talents.map_or(1.0, |talents| talents.damage_multiplier(source));
+            let flat_damage = talents.map_or(0.0, |talents| talents.flat_damage(source));
+            crit.chance += talents.map_or(0.0, |talents| talents.crit_chance(source));
+            crit.surge += talents.map_or(0.0, |talents| talents.crit_surge(source));
+            let accuracy = world.get::<Accuracy>(caster).map_or(BASE_ACCURACY, |accuracy| accuracy.0);
+            let target_ref = world.get_entity(target).ok_or(ActionError::NoEntity(target))?;
+            let miss_chance = target_ref.get::<Defense>().map_or(0.0, |defense| defense.0) - (accuracy - 1.0);
+            let armor = target_ref.get::<Armor>().map_or(0.0, |armor| armor.0);
+            // Without an rng every hit lands, does average damage and never crits
+            let (hit, roll, is_crit) = match world.get_resource_mut::<CombatRng>() {
+                Some(mut rng) => (
+                    !rng.roll(RollKind::Accuracy, caster, miss_chance),
+                    rng.fraction(RollKind::Damage, caster),
+                    rng.roll(RollKind::Crit, caster, crit.chance),
+                ),
+                None => (true, 0.5, false),
+            };
+            // Misses do no damage, so they aren't DamageDealt either
+            if !hit {
+                return Ok(());
+            }
+            let caster_ref = world.get_entity(caster).ok_or(ActionError::NoEntity(caster))?;
+            let bonus_dmg = caster_ref.get::<BonusDamage>().map_or(0.0, |bonus| bonus.0);
+            let mut amount = (spell_damage.hit(bonus_dmg, caster_ref.get::<WeaponDamage>(), roll) + flat_damage) * multiplier;
+            if is_crit {
+                amount *= 1.0 + crit.surge;
+            }
+            if spell_damage.damage_type.is_armored() {
+                amount *= 1.0 - armor_reduction(armor);
+            }
+
+            component_mut::<DamageTaken>(world, target)?.0 += amount;
+            *component_mut::<ThreatTable>(world, target)?.0.entry(caster).or_default() += amount;
+            world.send_event(DamageDealt { caster, target, source, amount, crit: is_crit });
+        }
+        ActionName::ModifyStat => {
+            let stat = param(params, PARAM_STAT, Params::id)?;
+            let value = f64::from(param(params, PARAM_VALUE, Params::float)?);
+            let timed = !applying.effect.duration.is_zero();
+            if timed && !applying.stacked {
+                return Ok(());
+            }
+            modify_stat(world, target, stat, value)?;
+            if timed {
+                let mut active = component_mut::<ActiveEffects>(world, target)?;
+                if let Some(active) = active.0.iter_mut().find(|active| active.is(applying.effect, caster)) {
+                    active.stats.push((stat, value));
+                }
+            }
+        }
+        ActionName::ModifyThreat => {
+            let threat = f64::from(param(params, PARAM_THREAT, Params::float)?);
+            *component_mut::<ThreatTable>(world, target)?.0.entry(caster).or_default() += threat;
+        }
+        ActionName::CallEffect => {
+            let id = param(params, PARAM_EFFECT, Params::id)?;
+            if applying.depth >= MAX_CALL_DEPTH {
+                return Err(ActionError::TooDeep(id));
+            }
+            let effect = {
+                let data = world.get_resource::<GameData>().ok_or(FieldError::invalid(PARAM_EFFECT, "no game data loaded"))?;
+                Effect::resolve(NodeRef(Id(id)), data, "effParams.Effect")?
+            };
+            apply_effect_at(world, caster, target, source, &effect, applying.depth + 1, problems);
+        }
+        ActionName::Other(name) => return Err(ActionError::Unsupported(name.clone())),
+    }
+    Ok(())
+}
+
+fn apply_sub_effect_at(world: &mut World, caster: Entity, target: Entity, source: u64, sub_effect: &SubEffect, applying: Applying, problems: &mut Vec<ActionError>) {
+    for action in &sub_effect.actions {
+        if let Err(e) = run_action(world, caster, target, source, action, applying, problems) {
+            problems.push(e);
+        }
+    }
+}
+
+fn apply_effect_at(world: &mut World, caster: Entity, target: Entity, source: u64, effect: &Effect, depth: usize, problems: &mut Vec<ActionError>) {
+    let stacked = if effect.duration.is_zero() {
+        true
+    } else {
+        match start_effect(world, caster, target, effect) {
+            Ok(stacked) => stacked,
+            Err(e) => {
+                problems.push(e);
+                return;
+            }
+        }
+    };
+    let applying = Applying { effect, stacked, depth };
+    // Subeffects with triggers only run when triggered, see trigger.rs
+    for sub_effect in effect.sub_effects.iter().filter(|sub_effect| sub_effect.triggers.is_empty()) {
+        apply_sub_effect_at(world, caster, target, source, sub_effect, applying, problems);
+    }
+}
+
+/// Runs every untriggered action of `effect`, starting or refreshing it on `target` if it has a duration.
+/// Actions that fail are skipped and returned
+pub fn apply_effect(world: &mut World, caster: Entity, target: Entity, source: u64, effect: &Effect) -> Vec<ActionError> {
+    let mut problems = vec![];
+    apply_effect_at(world, caster, target, source, effect, 0, &mut problems);
+    problems
+}
+
+/// Runs the actions of a triggered subeffect. `source` is the effect it belongs to, whatever it does lasts
+pub fn apply_sub_effect(world: &mut World, caster: Entity, target: Entity, source: u64, sub_effect: &SubEffect) -> Vec<ActionError> {
+    let mut problems = vec![];
+    let effect = Effect { id: source, ..Default::default() };
+    apply_sub_effect_at(world, caster, target, source, sub_effect, Applying { effect: &effect, stacked: true, depth: 0 }, &mut problems);
+    problems
+}
+
+pub fn run_effects(world: &mut World) {
+    let events: Vec<_> = world.resource_mut::<Events<ApplyEffect>>().drain().collect();
+    for ApplyEffect { caster, target, source, effect } in events {
+        for problem in apply_effect(world, caster, target, source, &effect) {
+            warn!("effect {}: {problem}", effect.id);
+        }
+        world.send_event(EffectApplied { caster, target, effect });
+    }
+}
+
+pub struct ActionPlugin;
+
+impl Plugin for ActionPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<ApplyEffect>()
+           .add_event::<DamageDealt>()
+           .add_event::<EffectApplied>()
+           .add_systems(Update, (expire_effects, run_effects).chain());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::effect::tests::effect_node;
+    use crate::parse::FieldValue;
+    use crate::types::variant_value;
+    use crate::utils::constants::BASE_LEVEL_DMG;
+
+    fn world_with(effects: Vec<crate::parse::NodeObjPair>) -> World {
+        let mut world = World::new();
+        world.insert_resource(GameData::from_nodes(effects).expect("test nodes have numeric ids"));
+        world
+    }
+
+    fn effect(id: u64, data: &World) -> Effect {
+        Effect::load(id, data.resource::<GameData>(), "test").expect("test effect parses")
+    }
+
+    #[test]
+    fn modify_stat() {
+        let power = Stat::Power.id();
+        let mut world = world_with(vec![
+            effect_node(1, &[("ModifyStat", vec![(PARAM_STAT, FieldValue::Id(Id(power))), (PARAM_VALUE, FieldValue::Float(50.0))])]),
+            effect_node(2, &[("ModifyStat", vec![(PARAM_STAT, FieldValue::Id(Id(9999))), (PARAM_VALUE, FieldValue::Float(0.5))])]),
+        ]);
+        let caster = world.spawn_empty().id();
+        let with_stats = world.spawn(Stats::default()).id();
+        let without_stats = world.spawn_empty().id();
+        let (power_bonus, unknown_stat) = (effect(1, &world), effect(2, &world));
+
+        // Stats has the stat, so it gets the bonus. Anything else ends up in StatModifiers
+        assert!(apply_effect(&mut world, caster, with_stats, 1, &power_bonus).is_empty());
+        assert_eq!(50.0, world.get::<Stats>(with_stats).unwrap().value(Stat::Power).bonus);
+        assert!(world.get::<StatModifiers>(with_stats).is_none());
+        assert!(apply_effect(&mut world, caster, with_stats, 2, &unknown_stat).is_empty());
+        assert_eq!(Some(&0.5), world.get::<StatModifiers>(with_stats).unwrap().0.get(&9999));
+
+        assert!(apply_effect(&mut world, caster, without_stats, 1, &power_bonus).is_empty());
+        assert_eq!(Some(&50.0), world.get::<StatModifiers>(without_stats).unwrap().0.get(&power));
+    }
+
+    #[test]
+    fn timed_modify_stat() {
+        let mut world = world_with(vec![
+            effect_node(1, &[("ModifyStat", vec![(PARAM_STAT, FieldValue::Id(Id(Stat::Power.id()))), (PARAM_VALUE, FieldValue::Float(50.0))])]),
+        ]);
+        world.init_resource::<SimTime>();
+        let caster = world.spawn_empty().id();
+        let target = world.spawn(Stats::default()).id();
+        let buff = Effect { duration: Duration::from_secs(6), stack_limit: 2, ..effect(1, &world) };
+        let power = |world: &World| world.get::<Stats>(target).unwrap().value(Stat::Power).bonus;
+
+        // Applying it again past the stack limit only refreshes it
+        for _ in 0..3 {
+            assert!(apply_effect(&mut world, caster, target, 1, &buff).is_empty());
+        }
+        assert_eq!(100.0, power(&world));
+        assert_eq!(2, world.get::<ActiveEffects>(target).unwrap().0[0].stacks);
+
+        world.resource_mut::<SimTime>().0 = Duration::from_secs(3);
+        assert!(apply_effect(&mut world, caster, target, 1, &buff).is_empty());
+        world.resource_mut::<SimTime>().0 = Duration::from_secs(6);
+        expire_effects(&mut world);
+        assert_eq!(100.0, power(&world), "the refresh at 3s lasts until 9s");
+
+        world.resource_mut::<SimTime>().0 = Duration::from_secs(9);
+        expire_effects(&mut world);
+        assert_eq!(0.0, power(&world));
+        assert!(world.get::<ActiveEffects>(target).unwrap().0.is_empty());
+
+        // One that ran out without expire_effects running starts over
+        assert!(apply_effect(&mut world, caster, target, 1, &buff).is_empty());
+        world.resource_mut::<SimTime>().0 = Duration::from_secs(15);
+        assert!(apply_effect(&mut world, caster, target, 1, &buff).is_empty());
+        assert_eq!(50.0, power(&world));
+    }
+
+    #[test]
+    fn modify_threat() {
+        let mut world = world_with(vec![effect_node(1, &[("ModifyThreat", vec![(PARAM_THREAT, FieldValue::Float(300.0))])])]);
+        let caster = world.spawn_empty().id();
+        let target = world.spawn_empty().id();
+        let taunt = effect(1, &world);
+        assert!(apply_effect(&mut world, caster, target, 1, &taunt).is_empty());
+        assert!(apply_effect(&mut world, caster, target, 1, &taunt).is_empty());
+        assert_eq!(Some(&600.0), world.get::<ThreatTable>(target).unwrap().0.get(&caster));
+        assert!(world.get::<DamageTaken>(target).is_none());
+    }
+
+    #[test]
+    fn call_effect() {
+        let mut world = world_with(vec![
+            effect_node(1, &[("CallEffect", vec![(PARAM_EFFECT, FieldValue::Id(Id(2)))])]),
+            effect_node(2, &[("ModifyThreat", vec![(PARAM_THREAT, FieldValue::Float(100.0))])]),
+            effect_node(3, &[("CallEffect", vec![(PARAM_EFFECT, FieldValue::Id(Id(3)))])]),
+            effect_node(4, &[("CallEffect", vec![(PARAM_EFFECT, FieldValue::Id(Id(5)))])]),
+        ]);
+        let caster = world.spawn_empty().id();
+        let target = world.spawn_empty().id();
+        let [calls_other, calls_itself, calls_missing] = [1, 3, 4].map(|id| effect(id, &world));
+
+        // The called effect runs right away, on the same target
+        assert!(apply_effect(&mut world, caster, target, 1, &calls_other).is_empty());
+        assert_eq!(Some(&100.0), world.get::<ThreatTable>(target).unwrap().0.get(&caster));
+
+        // An effect calling itself stops at the depth limit
+        let problems = apply_effect(&mut world, caster, target, 3, &calls_itself);
+        assert!(matches!(problems[..], [ActionError::TooDeep(3)]), "{problems:?}");
+
+        let problems = apply_effect(&mut world, caster, target, 4, &calls_missing);
+        assert!(matches!(problems[..], [ActionError::Field(FieldError::Invalid { .. })]), "{problems:?}");
+    }
+
+    #[test]
+    fn mitigation() {
+        let internal = variant_value(DamageType::ENUM, "Internal").expect("effDamageType variant");
+        let mut world = world_with(vec![
+            effect_node(1, &[("SpellDamage", vec![(PARAM_COEFFICIENT, FieldValue::Float(0.0)), (PARAM_SHP_MIN, FieldValue::Float(0.5))])]),
+            effect_node(2, &[("SpellDamage", vec![
+                (PARAM_COEFFICIENT, FieldValue::Float(0.0)),
+                (PARAM_SHP_MIN, FieldValue::Float(0.5)),
+                (PARAM_DAMAGE_TYPE, FieldValue::Enum(Id(internal))),
+            ])]),
+        ]);
+        world.insert_resource(CombatRng::new(0));
+        let caster = world.spawn_empty().id();
+        let accurate = world.spawn(Accuracy(2.0)).id();
+        let armored = world.spawn(Armor(10000.0)).id();
+        let dodges = world.spawn(Defense(1.0)).id();
+        let hit = effect(1, &world);
+        let internal = effect(2, &world);
+
+        assert!(apply_effect(&mut world, caster, armored, 1, &hit).is_empty());
+        assert_eq!(0.5 * BASE_LEVEL_DMG * (1.0 - armor_reduction(10000.0)), world.get::<DamageTaken>(armored).unwrap().0);
+        // Internal and elemental damage ignore armor
+        world.get_mut::<DamageTaken>(armored).unwrap().0 = 0.0;
+        assert!(apply_effect(&mut world, caster, armored, 2, &internal).is_empty());
+        assert_eq!(0.5 * BASE_LEVEL_DMG, world.get::<DamageTaken>(armored).unwrap().0);
+
+        // Defense is a miss chance, accuracy over 100% takes it away again
+        assert!(apply_effect(&mut world, caster, dodges, 1, &hit).is_empty());
+        assert!(world.get::<DamageTaken>(dodges).is_none());
+        assert!(apply_effect(&mut world, accurate, dodges, 1, &hit).is_empty());
+        assert_eq!(0.5 * BASE_LEVEL_DMG, world.get::<DamageTaken>(dodges).unwrap().0);
+    }
+
+    #[test]
+    fn damage() {
+        assert_eq!(0.5 * 1000.0 + 0.1 * BASE_LEVEL_DMG, hit_damage(0.5, (0.08, 0.12), 1000.0, None, 0.5));
+        let weapon = WeaponDamage { min: 100.0, max: 300.0 };
+        assert_eq!(200.0 * 0.5, hit_damage(0.0, (0.0, 0.0), 1000.0, Some((&weapon, -0.5)), 0.5));
+        assert_eq!(100.0 * 0.5, hit_damage(0.0, (0.0, 0.0), 1000.0, Some((&weapon, -0.5)), 0.0));
+    }
+
+    #[test]
+    fn unsupported_action() {
+        let mut world = World::new();
+        let caster = world.spawn(BonusDamage(100.0)).id();
+        let target = world.spawn_empty().id();
+        let effect = Effect {
+            id: 1,
+            sub_effects: vec![SubEffect {
+                actions: vec![EffAct {
+                    name: ActionName::Other("Resurrect".to_string()),
+                    params: Params::default(),
+                    function_tags: vec![],
+                    tag_exclusions: vec![],
+                }],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let problems = apply_effect(&mut world, caster, target, 1, &effect);
+        assert!(matches!(problems[..], [ActionError::Unsupported(ref name)] if name == "Resurrect"));
+        assert!(world.get::<DamageTaken>(target).is_none());
+    }
+}
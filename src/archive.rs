@@ -0,0 +1,319 @@
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+// "MYP\0" read as little endian
+static MYP_MAGIC: u32 = 0x0050594D;
+static HEADER_SIZE: usize = 0x1C;
+static TABLE_HEADER_SIZE: usize = 12;
+static ENTRY_SIZE: usize = 34;
+
+#[derive(Debug)]
+pub enum ArchiveError {
+    Io(io::Error),
+    BadMagic(u32),
+    UnsupportedVersion(u32),
+    NotFound(String),
+    Decompress(String),
+}
+
+impl fmt::Display for ArchiveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ArchiveError::Io(e) => write!(f, "io error: {e}"),
+            ArchiveError::BadMagic(magic) => write!(f, "not a MYP archive (magic {magic:#010x})"),
+            ArchiveError::UnsupportedVersion(v) => write!(f, "unsupported MYP version {v}"),
+            ArchiveError::NotFound(name) => write!(f, "file not found in archives: {name}"),
+            ArchiveError::Decompress(e) => write!(f, "could not decompress file: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for ArchiveError {}
+
+impl From<io::Error> for ArchiveError {
+    fn from(value: io::Error) -> Self {
+        ArchiveError::Io(value)
+    }
+}
+
+// Bob Jenkins' lookup3 hashlittle2 with both seeds 0, same as `hashlittle2` in jedipedia.js.
+// Returns (c, b), i.e. (primary, secondary)
+fn hashlittle2(k: &[u8]) -> (u32, u32) {
+    let mut a = 0xdeadbeefu32.wrapping_add(k.len() as u32);
+    let mut b = a;
+    let mut c = a;
+    let word = |i: usize| -> u32 {
+        (0..4).map(|j| k.get(i + j).map_or(0, |&byte| (byte as u32) << (8 * j))).sum()
+    };
+
+    let mut rest = k.len();
+    let mut offset = 0;
+    while rest > 12 {
+        a = a.wrapping_add(word(offset));
+        b = b.wrapping_add(word(offset + 4));
+        c = c.wrapping_add(word(offset + 8));
+        a = a.wrapping_sub(c); a ^= c.rotate_left(4);  c = c.wrapping_add(b);
+        b = b.wrapping_sub(a); b ^= a.rotate_left(6);  a = a.wrapping_add(c);
+        c = c.wrapping_sub(b); c ^= b.rotate_left(8);  b = b.wrapping_add(a);
+        a = a.wrapping_sub(c); a ^= c.rotate_left(16); c = c.wrapping_add(b);
+        b = b.wrapping_sub(a); b ^= a.rotate_left(19); a = a.wrapping_add(c);
+        c = c.wrapping_sub(b); c ^= b.rotate_left(4);  b = b.wrapping_add(a);
+        rest -= 12;
+        offset += 12;
+    }
+    if rest == 0 {
+        return (c, b);
+    }
+    // `word` zero-pads past the end, which is what the fallthrough switch does
+    a = a.wrapping_add(word(offset));
+    if rest > 4 { b = b.wrapping_add(word(offset + 4)); }
+    if rest > 8 { c = c.wrapping_add(word(offset + 8)); }
+
+    c ^= b; c = c.wrapping_sub(b.rotate_left(14));
+    a ^= c; a = a.wrapping_sub(c.rotate_left(11));
+    b ^= a; b = b.wrapping_sub(a.rotate_left(25));
+    c ^= b; c = c.wrapping_sub(b.rotate_left(16));
+    a ^= c; a = a.wrapping_sub(c.rotate_left(4));
+    b ^= a; b = b.wrapping_sub(a.rotate_left(14));
+    c ^= b; c = c.wrapping_sub(b.rotate_left(24));
+    (c, b)
+}
+
+/// Hash of a file path as it is stored in the file tables. Paths are case insensitive.
+pub fn hash_path(path: &str) -> u64 {
+    let (primary, secondary) = hashlittle2(path.to_lowercase().as_bytes());
+    ((primary as u64) << 32) | secondary as u64
+}
+
+fn read_u16(buf: &[u8], pos: usize) -> u16 {
+    u16::from_le_bytes(buf[pos..pos + 2].try_into().expect("slice is 2 bytes"))
+}
+
+fn read_u32(buf: &[u8], pos: usize) -> u32 {
+    u32::from_le_bytes(buf[pos..pos + 4].try_into().expect("slice is 4 bytes"))
+}
+
+fn read_u64(buf: &[u8], pos: usize) -> u64 {
+    u64::from_le_bytes(buf[pos..pos + 8].try_into().expect("slice is 8 bytes"))
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct FileEntry {
+    pub offset: u64,
+    pub header_size: u32,
+    pub compressed_size: u32,
+    pub size: u32,
+    pub hash: u64,
+    pub crc: u32,
+    pub compression: u16,
+}
+
+impl FileEntry {
+    fn from_bytes(buf: &[u8]) -> Self {
+        Self {
+            offset: read_u64(buf, 0),
+            header_size: read_u32(buf, 8),
+            compressed_size: read_u32(buf, 12),
+            size: read_u32(buf, 16),
+            hash: read_u64(buf, 20),
+            crc: read_u32(buf, 28),
+            compression: read_u16(buf, 32),
+        }
+    }
+
+    fn is_compressed(&self) -> bool {
+        self.compression != 0
+    }
+}
+
+/// A single MYP (.tor) archive. Only the file tables are kept in memory, file
+/// contents are read from disk on demand.
+#[derive(Debug)]
+pub struct Archive {
+    pub path: PathBuf,
+    pub version: u32,
+    files: HashMap<u64, FileEntry>,
+}
+
+impl Archive {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, ArchiveError> {
+        let path = path.as_ref().to_path_buf();
+        let mut file = File::open(&path)?;
+
+        let mut header = [0u8; HEADER_SIZE];
+        file.read_exact(&mut header)?;
+        let magic = read_u32(&header, 0);
+        if magic != MYP_MAGIC { return Err(ArchiveError::BadMagic(magic)) }
+        let version = read_u32(&header, 4);
+        if version != 5 && version != 6 { return Err(ArchiveError::UnsupportedVersion(version)) }
+
+        let mut files = HashMap::new();
+        let mut table_offset = read_u64(&header, 12);
+        while table_offset != 0 {
+            file.seek(SeekFrom::Start(table_offset))?;
+            let mut table_header = [0u8; TABLE_HEADER_SIZE];
+            file.read_exact(&mut table_header)?;
+            let capacity = read_u32(&table_header, 0) as usize;
+            table_offset = read_u64(&table_header, 4);
+
+            let mut table = vec![0u8; capacity * ENTRY_SIZE];
+            file.read_exact(&mut table)?;
+            for entry in table.chunks_exact(ENTRY_SIZE).map(FileEntry::from_bytes) {
+                // Unused slots have no offset
+                if entry.offset == 0 { continue }
+                files.insert(entry.hash, entry);
+            }
+        }
+
+        Ok(Self { path, version, files })
+    }
+
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = &FileEntry> {
+        self.files.values()
+    }
+
+    pub fn get_entry(&self, hash: u64) -> Option<&FileEntry> {
+        self.files.get(&hash)
+    }
+
+    pub fn contains(&self, path: &str) -> bool {
+        self.files.contains_key(&hash_path(path))
+    }
+
+    /// Reads and decompresses a file by path, e.g. `/resources/systemgenerated/client.gom`
+    pub fn read(&self, path: &str) -> Result<Vec<u8>, ArchiveError> {
+        self.read_hash(hash_path(path))
+            .ok_or_else(|| ArchiveError::NotFound(path.to_string()))?
+    }
+
+    pub fn read_hash(&self, hash: u64) -> Option<Result<Vec<u8>, ArchiveError>> {
+        self.get_entry(hash).map(|entry| self.read_entry(entry))
+    }
+
+    pub fn read_entry(&self, entry: &FileEntry) -> Result<Vec<u8>, ArchiveError> {
+        let mut file = File::open(&self.path)?;
+        file.seek(SeekFrom::Start(entry.offset + entry.header_size as u64))?;
+        if !entry.is_compressed() {
+            let mut data = vec![0u8; entry.size as usize];
+            file.read_exact(&mut data)?;
+            return Ok(data);
+        }
+
+        let mut compressed = vec![0u8; entry.compressed_size as usize];
+        file.read_exact(&mut compressed)?;
+        match self.version {
+            // Version 6 archives use zstd, older ones zlib-wrapped deflate
+            6 => zstd::bulk::decompress(&compressed, entry.size as usize)
+                .map_err(|e| ArchiveError::Decompress(e.to_string())),
+            _ => {
+                let mut data = Vec::with_capacity(entry.size as usize);
+                flate2::read::ZlibDecoder::new(&compressed[..])
+                    .read_to_end(&mut data)
+                    .map_err(|e| ArchiveError::Decompress(e.to_string()))?;
+                Ok(data)
+            },
+        }
+    }
+}
+
+/// All archives of a game install, looked up as one file system
+#[derive(Debug, Default)]
+pub struct Archives(pub Vec<Archive>);
+
+impl Archives {
+    /// Opens every .tor file in `dir`, e.g. `Star Wars - The Old Republic/Assets`
+    pub fn open_dir<P: AsRef<Path>>(dir: P) -> Result<Self, ArchiveError> {
+        let mut paths = fs::read_dir(dir)?
+            .map(|entry| entry.map(|e| e.path()))
+            .collect::<Result<Vec<_>, _>>()?;
+        paths.retain(|path| path.extension().is_some_and(|ext| ext == "tor"));
+        paths.sort();
+        Ok(Self(paths.iter().map(Archive::open).collect::<Result<_, _>>()?))
+    }
+
+    pub fn contains(&self, path: &str) -> bool {
+        let hash = hash_path(path);
+        self.0.iter().any(|archive| archive.get_entry(hash).is_some())
+    }
+
+    pub fn read(&self, path: &str) -> Result<Vec<u8>, ArchiveError> {
+        let hash = hash_path(path);
+        self.0.iter()
+            .find_map(|archive| archive.read_hash(hash))
+            .ok_or_else(|| ArchiveError::NotFound(path.to_string()))?
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    #[test]
+    fn hashlittle2_reference() {
+        // Values from the lookup3.c self test
+        assert_eq!((0xdeadbeef, 0xdeadbeef), hashlittle2(b""));
+        assert_eq!((0x17770551, 0xce7226e6), hashlittle2(b"Four score and seven years ago"));
+    }
+
+    #[test]
+    fn hash_path_ignores_case() {
+        assert_eq!(hash_path("/resources/systemgenerated/client.gom"),
+                   hash_path("/RESOURCES/SystemGenerated/Client.gom"));
+    }
+
+    /// Writes `files` as (path, compression, content, uncompressed size) to a temporary archive
+    pub(crate) fn write_archive(name: &str, version: u32, files: &[(&str, u16, &[u8], u32)]) -> PathBuf {
+        let mut data = vec![0u8; HEADER_SIZE];
+        data[0..4].copy_from_slice(&MYP_MAGIC.to_le_bytes());
+        data[4..8].copy_from_slice(&version.to_le_bytes());
+        data[12..20].copy_from_slice(&(HEADER_SIZE as u64).to_le_bytes());
+
+        let table_start = data.len();
+        data.extend((files.len() as u32).to_le_bytes());
+        data.extend(0u64.to_le_bytes());
+        let mut offset = table_start + TABLE_HEADER_SIZE + files.len() * ENTRY_SIZE;
+        for (name, compression, content, size) in files {
+            data.extend((offset as u64).to_le_bytes());
+            data.extend(0u32.to_le_bytes());
+            data.extend((content.len() as u32).to_le_bytes());
+            data.extend(size.to_le_bytes());
+            data.extend(hash_path(name).to_le_bytes());
+            data.extend(0u32.to_le_bytes());
+            data.extend(compression.to_le_bytes());
+            offset += content.len();
+        }
+        for (_, _, content, _) in files {
+            data.extend_from_slice(content);
+        }
+
+        let path = std::env::temp_dir().join(format!("swtor-sim-test-{name}.tor"));
+        fs::write(&path, data).expect("can write test archive");
+        path
+    }
+
+    #[test]
+    fn read_archive() -> Result<(), ArchiveError> {
+        let content = b"some node data";
+        let compressed = zstd::bulk::compress(content, 0).expect("can compress");
+        let path = write_archive("read_archive", 6, &[
+            ("/resources/raw.txt", 0, content, content.len() as u32),
+            ("/resources/compressed.txt", 1, &compressed, content.len() as u32),
+        ]);
+        let archive = Archive::open(&path)?;
+        assert_eq!(2, archive.len());
+        assert_eq!(content.to_vec(), archive.read("/resources/raw.txt")?);
+        assert_eq!(content.to_vec(), archive.read("/Resources/Compressed.txt")?);
+        assert!(matches!(archive.read("/resources/missing.txt"), Err(ArchiveError::NotFound(_))));
+        fs::remove_file(path)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,165 @@
+#![allow(dead_code)]
+
+// Monte Carlo runs of the same fight. Every iteration gets its own seed and runs on its own
+// thread from rayon's pool, and the results are summarized per stat.
+
+use std::collections::{BTreeMap, HashMap};
+use rayon::prelude::*;
+use crate::simulation::{SimResult, Simulation};
+
+/// z for a two-sided 95% confidence interval
+static Z_95: f64 = 1.96;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Summary {
+    pub samples: usize,
+    pub mean: f64,
+    /// Sample standard deviation
+    pub std_dev: f64,
+    pub min: f64,
+    pub max: f64,
+    /// 5th, 25th, 50th, 75th and 95th percentile
+    pub percentiles: [f64; 5],
+    /// 95% confidence interval of the mean
+    pub ci_95: (f64, f64),
+}
+
+pub static PERCENTILES: [f64; 5] = [5.0, 25.0, 50.0, 75.0, 95.0];
+
+/// Linearly interpolated percentile `p` (0 to 100) of sorted values
+fn percentile(sorted: &[f64], p: f64) -> f64 {
+    let rank = p / 100.0 * (sorted.len() - 1) as f64;
+    let (low, high) = (rank.floor() as usize, rank.ceil() as usize);
+    sorted[low] + (sorted[high] - sorted[low]) * (rank - low as f64)
+}
+
+impl Summary {
+    pub fn of(mut values: Vec<f64>) -> Self {
+        if values.is_empty() {
+            return Summary::default();
+        }
+        values.sort_by(f64::total_cmp);
+        let n = values.len() as f64;
+        let mean = values.iter().sum::<f64>() / n;
+        let variance = if values.len() > 1 {
+            values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (n - 1.0)
+        } else {
+            0.0
+        };
+        let std_dev = variance.sqrt();
+        let margin = Z_95 * std_dev / n.sqrt();
+        Summary {
+            samples: values.len(),
+            mean,
+            std_dev,
+            min: values[0],
+            max: values[values.len() - 1],
+            percentiles: PERCENTILES.map(|p| percentile(&values, p)),
+            ci_95: (mean - margin, mean + margin),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SourceSummary {
+    pub damage: Summary,
+    /// Only over iterations where the source hit at all
+    pub crit_rate: Summary,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatchResult {
+    pub iterations: usize,
+    pub dps: Summary,
+    pub crit_rate: Summary,
+    /// By ability id, or effect id for procs
+    pub by_source: BTreeMap<u64, SourceSummary>,
+}
+
+impl BatchResult {
+    pub fn from_results(results: &[SimResult]) -> Self {
+        let mut damage: HashMap<u64, Vec<f64>> = HashMap::new();
+        let mut crit_rates: HashMap<u64, Vec<f64>> = HashMap::new();
+        for (i, result) in results.iter().enumerate() {
+            for (&source, stats) in &result.by_source {
+                // Iterations where a source did nothing count as 0 damage
+                damage.entry(source).or_insert_with(|| vec![0.0; i]).push(stats.damage);
+                crit_rates.entry(source).or_default().extend(stats.crit_rate());
+            }
+            for values in damage.values_mut() {
+                values.resize(i + 1, 0.0);
+            }
+        }
+        let by_source = damage.into_iter().map(|(source, damage)| {
+            let crit_rate = Summary::of(crit_rates.remove(&source).unwrap_or_default());
+            (source, SourceSummary { damage: Summary::of(damage), crit_rate })
+        }).collect();
+        BatchResult {
+            iterations: results.len(),
+            dps: Summary::of(results.iter().map(|r| r.dps).collect()),
+            crit_rate: Summary::of(results.iter().filter_map(|r| r.total.crit_rate()).collect()),
+            by_source,
+        }
+    }
+}
+
+/// Runs `iterations` simulations on all cores. `simulation` builds the one for a seed,
+/// e.g. `|seed| Simulation::new(data.clone()).seed(seed)`.
+/// The seeds are `first_seed`, `first_seed + 1` and so on
+pub fn run_batch<F>(iterations: usize, first_seed: u64, simulation: F) -> BatchResult
+where
+    F: Fn(u64) -> Simulation + Sync,
+{
+    let results: Vec<SimResult> = (0..iterations as u64)
+        .into_par_iter()
+        .map(|i| simulation(first_seed + i).run())
+        .collect();
+    BatchResult::from_results(&results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use crate::simulation::DamageStats;
+
+    #[test]
+    fn summary() {
+        let summary = Summary::of(vec![4.0, 1.0, 3.0, 2.0, 5.0]);
+        assert_eq!(3.0, summary.mean);
+        assert_eq!(2.5f64.sqrt(), summary.std_dev);
+        assert_eq!((1.0, 5.0), (summary.min, summary.max));
+        assert_eq!([1.2, 2.0, 3.0, 4.0, 4.8], summary.percentiles.map(|p| (p * 1e9).round() / 1e9));
+        let margin = 1.96 * 2.5f64.sqrt() / 5f64.sqrt();
+        assert_eq!((3.0 - margin, 3.0 + margin), summary.ci_95);
+        assert_eq!(Summary::default(), Summary::of(vec![]));
+    }
+
+    #[test]
+    fn sources() {
+        let result = |stats: Vec<(u64, DamageStats)>| SimResult {
+            duration: Duration::from_secs(1),
+            dps: stats.iter().map(|(_, s)| s.damage).sum(),
+            total: DamageStats::default(),
+            by_source: stats.into_iter().collect(),
+            activations: 0,
+        };
+        let batch = BatchResult::from_results(&[
+            result(vec![(1, DamageStats { damage: 10.0, hits: 2, crits: 1 })]),
+            result(vec![(2, DamageStats { damage: 4.0, hits: 1, crits: 0 })]),
+        ]);
+        assert_eq!(2, batch.iterations);
+        assert_eq!(7.0, batch.dps.mean);
+        assert_eq!(5.0, batch.by_source[&1].damage.mean);
+        assert_eq!(2.0, batch.by_source[&2].damage.mean);
+        assert_eq!(1, batch.by_source[&1].crit_rate.samples);
+        assert_eq!(0.5, batch.by_source[&1].crit_rate.mean);
+    }
+
+    #[test]
+    fn batch() {
+        let batch = run_batch(4, 0, |seed| Simulation::new(Default::default()).seed(seed).fight_length(Duration::from_secs(5)));
+        assert_eq!(4, batch.iterations);
+        assert_eq!(4, batch.dps.samples);
+    }
+}
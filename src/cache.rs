@@ -0,0 +1,109 @@
+// Precompiled game data, written by build.rs and embedded in the binary.
+// Also included by build.rs next to parse.rs, so it can't use anything else from the crate.
+//
+// Layout: magic, format version (u32 LE), crc32 of the body (u32 LE), then the body,
+// which is zstd compressed MessagePack of a [`CacheData`]. build.rs parses data/*.json once,
+// the nodes are stored parsed, so loading the cache doesn't go through the JSON shape again.
+
+use std::collections::HashMap;
+use std::fmt;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use crate::parse::NodeObjPair;
+
+pub static CACHE_MAGIC: [u8; 4] = *b"SWSC";
+/// Bump whenever the layout or the node model changes
+pub static CACHE_VERSION: u32 = 4;
+pub static CACHE_FILE: &str = "game_data.bin";
+/// Node kinds that go into the cache, read from `data/{prefix}.json`
+pub static NODE_PREFIXES: [&str; 6] = ["abl", "dis", "tal", "apc", "itm", "npc"];
+/// Names and descriptions the nodes use, read from `data/{STRINGS_FILE}`
+pub static STRINGS_FILE: &str = "strings.json";
+/// GOM enum and field names by id, which build.rs generates the enums and field lookup from
+pub static GOM_FILE: &str = "GOM.json";
+/// Variant names of the GOM enums by enum id
+pub static CLIENT_GOM_FILE: &str = "clientGom.json";
+
+static HEADER_LEN: usize = 12;
+static ZSTD_LEVEL: i32 = 19;
+
+#[derive(Debug)]
+pub enum CacheError {
+    BadMagic,
+    Version(u32),
+    Checksum { expected: u32, found: u32 },
+    Decompress(String),
+    Decode(String),
+}
+
+impl fmt::Display for CacheError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CacheError::BadMagic => write!(f, "not a game data cache"),
+            CacheError::Version(v) => write!(f, "cache version {v}, expected {CACHE_VERSION}"),
+            CacheError::Checksum { expected, found } => write!(f, "cache checksum {found:08x}, expected {expected:08x}"),
+            CacheError::Decompress(e) => write!(f, "couldn't decompress cache: {e}"),
+            CacheError::Decode(e) => write!(f, "couldn't decode cache: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for CacheError {}
+
+/// Strings by bucket and string id, e.g. "str.abl" -> id -> "Force Lightning"
+pub type StringMap = HashMap<String, HashMap<u64, String>>;
+
+/// Everything in the cache
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct CacheData {
+    pub nodes: Vec<NodeObjPair>,
+    pub strings: StringMap,
+}
+
+pub fn encode<T: Serialize>(value: &T) -> Vec<u8> {
+    let packed = rmp_serde::to_vec(value).expect("cache data should serialize");
+    let body = zstd::encode_all(packed.as_slice(), ZSTD_LEVEL).expect("should compress in memory");
+    let mut bytes = Vec::with_capacity(HEADER_LEN + body.len());
+    bytes.extend_from_slice(&CACHE_MAGIC);
+    bytes.extend_from_slice(&CACHE_VERSION.to_le_bytes());
+    bytes.extend_from_slice(&crc32fast::hash(&body).to_le_bytes());
+    bytes.extend_from_slice(&body);
+    bytes
+}
+
+pub fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, CacheError> {
+    if bytes.len() < HEADER_LEN || bytes[0..4] != CACHE_MAGIC {
+        return Err(CacheError::BadMagic);
+    }
+    let version = u32::from_le_bytes(bytes[4..8].try_into().unwrap());
+    if version != CACHE_VERSION {
+        return Err(CacheError::Version(version));
+    }
+    let expected = u32::from_le_bytes(bytes[8..12].try_into().unwrap());
+    let body = &bytes[HEADER_LEN..];
+    let found = crc32fast::hash(body);
+    if found != expected {
+        return Err(CacheError::Checksum { expected, found });
+    }
+    let packed = zstd::decode_all(body).map_err(|e| CacheError::Decompress(e.to_string()))?;
+    rmp_serde::from_slice(&packed).map_err(|e| CacheError::Decode(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() -> Result<(), CacheError> {
+        let value = serde_json::json!([{ "node": { "id": "123" }, "obj": [1, -2, 3.5, "four"] }]);
+        let mut bytes = encode(&value);
+        assert_eq!(value, decode::<serde_json::Value>(&bytes)?);
+
+        let last = bytes.len() - 1;
+        bytes[last] ^= 1;
+        assert!(matches!(decode::<serde_json::Value>(&bytes), Err(CacheError::Checksum { .. })));
+        bytes[4] = 0;
+        assert!(matches!(decode::<serde_json::Value>(&bytes), Err(CacheError::Version(0))));
+        assert!(matches!(decode::<serde_json::Value>(b"SWS"), Err(CacheError::BadMagic)));
+        Ok(())
+    }
+}
@@ -0,0 +1,114 @@
+#![allow(dead_code)]
+
+// Diminishing returns of secondary stats. Every point of rating gives a bit less than the one
+// before, approaching a cap. The curves change between expansions, so they're read from
+// data/diminishing_returns.json at runtime, the copy compiled in is only a fallback.
+// Only the alacrity curve is checked against the game so far, its magic is fitted to the 1.4s GCD
+// breakpoint. Critical is the curve the old `Gear::get_crit_surge` used. Accuracy, defense, shield
+// and absorb use the community stat formulas and still need checking against tooltips, add them
+// to the table in the tests when they are.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use bevy::prelude::*;
+use serde::Deserialize;
+use crate::stats::Stat;
+
+pub static DR_FILE: &str = "diminishing_returns.json";
+static BUILTIN_DR: &str = include_str!("../data/diminishing_returns.json");
+
+#[derive(Debug)]
+pub enum DrError {
+    Io { file: String, err: io::Error },
+    Json { file: String, err: serde_json::Error },
+}
+
+impl fmt::Display for DrError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DrError::Io { file, err } => write!(f, "{file}: {err}"),
+            DrError::Json { file, err } => write!(f, "{file}: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for DrError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct DrCurve {
+    /// Highest percentage, as a fraction, the rating can give
+    pub cap: f64,
+    /// Rating per level for the first 1%
+    pub magic: f64,
+}
+
+impl DrCurve {
+    /// Percentage as a fraction
+    pub fn percent(&self, rating: f64, level: f64) -> f64 {
+        self.cap * (1.0 - (1.0 - 0.01 / self.cap).powf(rating / level / self.magic))
+    }
+}
+
+/// Without curves, ratings give nothing
+#[derive(Resource, Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct DiminishingReturns {
+    pub level: f64,
+    pub curves: HashMap<Stat, DrCurve>,
+}
+
+impl DiminishingReturns {
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+
+    /// The curves in data/ when the binary was built
+    pub fn builtin() -> Self {
+        Self::from_json(BUILTIN_DR).expect("data/diminishing_returns.json is valid")
+    }
+
+    /// Reads [`DR_FILE`] in `data_path`
+    pub fn load<P: AsRef<Path>>(data_path: P) -> Result<Self, DrError> {
+        let path = data_path.as_ref().join(DR_FILE);
+        let file = path.display().to_string();
+        let json = fs::read_to_string(&path).map_err(|err| DrError::Io { file: file.clone(), err })?;
+        Self::from_json(&json).map_err(|err| DrError::Json { file, err })
+    }
+
+    /// Percentage, as a fraction, `rating` of `stat` gives
+    pub fn percent(&self, stat: Stat, rating: f64) -> f64 {
+        self.curves.get(&stat).map_or(0.0, |curve| curve.percent(rating, self.level))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use crate::timeline::scaled;
+    use crate::utils::constants::GCD;
+
+    /// (stat, rating, percentage on the character sheet tooltip) read in game
+    static TOOLTIPS: &[(Stat, f64, f64)] = &[
+        // The least alacrity that makes every GCD 1.4s, see the README
+        (Stat::Alacrity, 2054.0, 7.14),
+    ];
+
+    #[test]
+    fn tooltips() {
+        let dr = DiminishingReturns::builtin();
+        for &(stat, rating, tooltip) in TOOLTIPS {
+            let percent = (dr.percent(stat, rating) * 10000.0).round() / 100.0;
+            assert_eq!(tooltip, percent, "{stat} at {rating}");
+        }
+        for stat in [Stat::Critical, Stat::Alacrity, Stat::Accuracy, Stat::Defense, Stat::Shield, Stat::Absorb] {
+            assert!(dr.curves.contains_key(&stat), "no curve for {stat}");
+        }
+        // The breakpoint is the point of the alacrity row
+        let gcd = |rating| scaled(GCD, dr.percent(Stat::Alacrity, rating));
+        assert!(gcd(2053.0) > Duration::from_millis(1400));
+        assert!(gcd(2054.0) <= Duration::from_millis(1400));
+    }
+}
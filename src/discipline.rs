@@ -0,0 +1,450 @@
+#![allow(dead_code)]
+
+// Discipline trees. A dis.* node has rows of three options, one of which is picked per row. The
+// abilities every character of the discipline gets are on its apc.* node instead, which is how
+// jedipedia.js lists them (`openClassChanges`). Both unlock at a level.
+// Options and baseline abilities are abl.* or tal.* nodes, see `talent` for what a tal.* does.
+//
+// Builds are written as a combat style and a letter per row, e.g. `darkness:RLRRMLLM`, or with
+// the names of the chosen options instead, e.g. `darkness:Name 1,Name 2,...`.
+
+use std::fmt;
+use std::str::FromStr;
+use crate::ecs_system::{CombatStyle, TreeChoice, TreeChoices};
+use crate::effect::key_of;
+use crate::game_data::GameData;
+use crate::gom::{FieldError, Fields};
+use crate::parse::{FieldValue, NodeObjPair};
+
+/// Level of each row, then the keys of its options in `disModAbilities`
+static DIS_LEVEL_MOD_LIST: &str = "disLevelModList";
+static DIS_MOD_ABILITIES: &str = "disModAbilities";
+/// Baseline abilities and talents of an apc.* node, by field id as in jedipedia.js. Both look up
+/// the node's id and get a class with the level it's granted at
+static APC_ABILITIES_ID: u64 = 4611686061183631195;
+static APC_ABILITY_LEVEL_ID: u64 = 4611686031022270044;
+static APC_TALENTS_ID: u64 = 4611686296953210012;
+static APC_TALENT_LEVEL_ID: u64 = 4611686296953210009;
+static ABL_IS_PASSIVE: &str = "ablIsPassive";
+
+pub const OPTIONS: usize = 3;
+
+#[derive(Debug)]
+pub enum DisciplineError {
+    UnknownDiscipline(String),
+    /// No apc.* node with the discipline's baseline abilities
+    NoBaseline(String),
+    Field { discipline: String, err: FieldError },
+    /// A build needs exactly one choice per row
+    ChoiceCount { expected: usize, found: usize },
+    /// An option or baseline ability that isn't in the game data
+    UnknownNode { discipline: String, id: u64 },
+}
+
+impl fmt::Display for DisciplineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DisciplineError::UnknownDiscipline(fqn) => write!(f, "no discipline node {fqn}"),
+            DisciplineError::NoBaseline(fqn) => write!(f, "{fqn}: no apc node with its baseline abilities"),
+            DisciplineError::Field { discipline, err } => write!(f, "{discipline}: {err}"),
+            DisciplineError::ChoiceCount { expected, found } => write!(f, "expected {expected} choices, found {found}"),
+            DisciplineError::UnknownNode { discipline, id } => write!(f, "{discipline}: no node {id}"),
+        }
+    }
+}
+
+impl std::error::Error for DisciplineError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Row {
+    pub level: u64,
+    /// Left, middle and right
+    pub options: [u64; OPTIONS],
+}
+
+impl Row {
+    pub fn option(&self, choice: TreeChoice) -> u64 {
+        self.options[choice as usize]
+    }
+}
+
+/// Granted to everyone in the discipline
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Baseline {
+    pub level: u64,
+    pub ability: u64,
+    /// Talents and passive abilities, nothing to put on a bar
+    pub passive: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Discipline {
+    pub fqn: String,
+    pub rows: Vec<Row>,
+    pub baseline: Vec<Baseline>,
+}
+
+fn level_of(key: &FieldValue, field: &str, i: usize) -> Result<u64, FieldError> {
+    key_of(key).ok_or_else(|| FieldError::invalid(field, format!("[{i}] has key {key:?}")))
+}
+
+fn is_passive(pair: &NodeObjPair) -> bool {
+    pair.node.fqn.starts_with("tal.") || pair.obj.get_bool(ABL_IS_PASSIVE).unwrap_or(false)
+}
+
+/// Ids and levels of the baseline abilities or talents in field `list_id` of an apc.* node
+fn granted(apc: &NodeObjPair, list_id: u64, level_id: u64) -> Result<Vec<(u64, u64)>, FieldError> {
+    let field = format!("{list_id}");
+    let lookup = match apc.obj.get_by_id(list_id) {
+        None => return Err(FieldError::Missing(field)),
+        Some(FieldValue::LookupList(lookup)) => lookup,
+        Some(other) => return Err(FieldError::WrongType { field, expected: "LookupList", found: other.type_name() }),
+    };
+    lookup.0.iter().enumerate().map(|(i, (key, class))| {
+        let id = key_of(key).ok_or_else(|| FieldError::invalid(&field, format!("[{i}] has key {key:?}")))?;
+        let level = match class {
+            FieldValue::ClassView(class) => class.get_by_id(level_id).and_then(key_of),
+            _ => None,
+        };
+        let level = level.ok_or_else(|| FieldError::invalid(&field, format!("[{i}] has no level {level_id}")))?;
+        Ok((id, level))
+    }).collect()
+}
+
+impl Discipline {
+    /// The dis.* node of a combat style
+    pub fn load(combat_style: &CombatStyle, data: &GameData) -> Result<Self, DisciplineError> {
+        let fqn = combat_style.get_dis_fqn();
+        let pair = data.get_by_fqn(&fqn).ok_or(DisciplineError::UnknownDiscipline(fqn))?;
+        Self::from_pair(pair, data)
+    }
+
+    /// Every discipline in the game data
+    pub fn all(data: &GameData) -> impl Iterator<Item = Result<Self, DisciplineError>> + '_ {
+        data.nodes("dis").map(|pair| Self::from_pair(pair, data))
+    }
+
+    pub fn from_pair(pair: &NodeObjPair, data: &GameData) -> Result<Self, DisciplineError> {
+        let fqn = pair.node.fqn.clone();
+        let field_err = |err| DisciplineError::Field { discipline: fqn.clone(), err };
+        let obj = &pair.obj;
+        let mods = obj.get_lookup(DIS_MOD_ABILITIES).map_err(field_err)?;
+        let rows = obj.get_lookup(DIS_LEVEL_MOD_LIST).map_err(field_err)?
+            .iter()
+            .enumerate()
+            .map(|(i, (level, options))| {
+                let level = level_of(level, DIS_LEVEL_MOD_LIST, i)?;
+                let options = match options {
+                    FieldValue::List(list) => &list.0,
+                    other => return Err(FieldError::WrongType { field: format!("{DIS_LEVEL_MOD_LIST}[{i}]"), expected: "List", found: other.type_name() }),
+                };
+                let ids = options.iter()
+                    .map(|key| mods.iter()
+                                   .find(|(k, _)| k == key)
+                                   .and_then(|(_, id)| key_of(id))
+                                   .ok_or_else(|| FieldError::invalid(DIS_MOD_ABILITIES, format!("no ability id for {key:?}"))))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let options = ids.try_into().map_err(|ids: Vec<u64>| {
+                    FieldError::invalid(DIS_LEVEL_MOD_LIST, format!("[{i}] has {} options", ids.len()))
+                })?;
+                Ok(Row { level, options })
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(field_err)?;
+
+        // apc.<class>.<advanced class>.<discipline>, discipline names are unique
+        let name = fqn.rsplit('.').next().unwrap_or_default();
+        let apc = data.nodes("apc")
+            .find(|apc| apc.node.fqn.rsplit('.').next() == Some(name))
+            .ok_or_else(|| DisciplineError::NoBaseline(fqn.clone()))?;
+        let apc_err = |err| DisciplineError::Field { discipline: apc.node.fqn.clone(), err };
+        let mut granted_ids = granted(apc, APC_ABILITIES_ID, APC_ABILITY_LEVEL_ID).map_err(apc_err)?;
+        granted_ids.extend(granted(apc, APC_TALENTS_ID, APC_TALENT_LEVEL_ID).map_err(apc_err)?);
+        let baseline = granted_ids.into_iter().map(|(id, level)| {
+            let pair = data.get(id).ok_or_else(|| DisciplineError::UnknownNode { discipline: fqn.clone(), id })?;
+            Ok(Baseline { level, ability: id, passive: is_passive(pair) })
+        }).collect::<Result<_, _>>()?;
+        Ok(Discipline { fqn, rows, baseline })
+    }
+
+    /// Checks there's one choice per row and that the chosen nodes exist
+    pub fn validate(&self, choices: &[TreeChoice], data: &GameData) -> Result<(), DisciplineError> {
+        for id in self.chosen(choices)? {
+            if data.get(id).is_none() {
+                return Err(DisciplineError::UnknownNode { discipline: self.fqn.clone(), id });
+            }
+        }
+        Ok(())
+    }
+
+    /// Node of the chosen option in every row
+    pub fn chosen(&self, choices: &[TreeChoice]) -> Result<Vec<u64>, DisciplineError> {
+        if choices.len() != self.rows.len() {
+            return Err(DisciplineError::ChoiceCount { expected: self.rows.len(), found: choices.len() });
+        }
+        Ok(self.rows.iter().zip(choices).map(|(row, &choice)| row.option(choice)).collect())
+    }
+
+    /// Baseline abilities and chosen options a character at `level` has
+    pub fn abilities(&self, choices: &[TreeChoice], level: u64) -> Result<Vec<u64>, DisciplineError> {
+        let chosen = self.chosen(choices)?;
+        let baseline = self.baseline.iter().filter(|base| base.level <= level).map(|base| base.ability);
+        let rows = self.rows.iter().zip(chosen).filter(|(row, _)| row.level <= level).map(|(_, id)| id);
+        Ok(baseline.chain(rows).collect())
+    }
+
+    /// One line per row like "row 4 (level 39): A / B / C", with the chosen option in brackets
+    pub fn describe(&self, choices: Option<&[TreeChoice]>, data: &GameData) -> Vec<String> {
+        let name = |id| data.name(id).map_or_else(|| id.to_string(), str::to_string);
+        self.rows.iter().enumerate().map(|(i, row)| {
+            let chosen = choices.and_then(|choices| choices.get(i)).map(|&choice| choice as usize);
+            let options: Vec<_> = row.options.iter().enumerate().map(|(j, &id)| match chosen == Some(j) {
+                true => format!("[{}]", name(id)),
+                false => name(id),
+            }).collect();
+            format!("row {} (level {}): {}", i + 1, row.level, options.join(" / "))
+        }).collect()
+    }
+}
+
+#[derive(Debug)]
+pub enum BuildCodeError {
+    /// No `:` between the combat style and the choices
+    NoCombatStyle(String),
+    UnknownCombatStyle(String),
+    UnknownChoice(char),
+    ChoiceCount { expected: usize, found: usize },
+    /// No option in the row has this name
+    UnknownName { row: usize, name: String },
+    Discipline(DisciplineError),
+}
+
+impl fmt::Display for BuildCodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BuildCodeError::NoCombatStyle(code) => write!(f, "{code} doesn't start with a combat style, e.g. darkness:"),
+            BuildCodeError::UnknownCombatStyle(style) => write!(f, "unknown combat style {style}"),
+            BuildCodeError::UnknownChoice(c) => write!(f, "unknown choice {c}, expected L, M or R"),
+            BuildCodeError::ChoiceCount { expected, found } => write!(f, "expected {expected} choices, found {found}"),
+            BuildCodeError::UnknownName { row, name } => write!(f, "row {row} has no option {name}"),
+            BuildCodeError::Discipline(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl std::error::Error for BuildCodeError {}
+
+impl From<DisciplineError> for BuildCodeError {
+    fn from(value: DisciplineError) -> Self {
+        BuildCodeError::Discipline(value)
+    }
+}
+
+fn split_code(code: &str) -> Result<(CombatStyle, &str), BuildCodeError> {
+    let (style, choices) = code.split_once(':').ok_or_else(|| BuildCodeError::NoCombatStyle(code.to_string()))?;
+    let style = style.trim();
+    let combat_style = CombatStyle::from_str(style).map_err(|_| BuildCodeError::UnknownCombatStyle(style.to_string()))?;
+    Ok((combat_style, choices.trim()))
+}
+
+fn to_array(choices: Vec<TreeChoice>) -> Result<[TreeChoice; 8], BuildCodeError> {
+    choices.try_into().map_err(|choices: Vec<_>| BuildCodeError::ChoiceCount { expected: 8, found: choices.len() })
+}
+
+/// Letters only, see [`TreeChoices::from_code`] for names
+impl FromStr for TreeChoices {
+    type Err = BuildCodeError;
+
+    fn from_str(code: &str) -> Result<Self, Self::Err> {
+        let (combat_style, letters) = split_code(code)?;
+        let choices = letters.chars()
+            .map(|c| TreeChoice::from_str(c.encode_utf8(&mut [0; 4])).map_err(|_| BuildCodeError::UnknownChoice(c)))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(TreeChoices { combat_style, choices: to_array(choices)? })
+    }
+}
+
+impl fmt::Display for TreeChoices {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:", self.combat_style.to_string().to_lowercase())?;
+        self.choices.iter().try_for_each(|choice| write!(f, "{choice}"))
+    }
+}
+
+impl TreeChoices {
+    /// Parses a build with letters or option names, checked against the discipline
+    pub fn from_code(code: &str, data: &GameData) -> Result<Self, BuildCodeError> {
+        let (combat_style, rest) = split_code(code)?;
+        let discipline = Discipline::load(&combat_style, data)?;
+        let letters = !rest.contains(',') && rest.chars().all(|c| TreeChoice::from_str(c.encode_utf8(&mut [0; 4])).is_ok());
+        let choices = match letters {
+            true => Self::from_str(code)?,
+            false => {
+                let names: Vec<_> = rest.split(',').map(str::trim).collect();
+                if names.len() != discipline.rows.len() {
+                    return Err(BuildCodeError::ChoiceCount { expected: discipline.rows.len(), found: names.len() });
+                }
+                let choices = discipline.rows.iter().zip(names).enumerate().map(|(i, (row, name))| {
+                    [TreeChoice::Left, TreeChoice::Middle, TreeChoice::Right].into_iter()
+                        .find(|&choice| data.name(row.option(choice)).is_some_and(|option| option.eq_ignore_ascii_case(name)))
+                        .ok_or_else(|| BuildCodeError::UnknownName { row: i + 1, name: name.to_string() })
+                }).collect::<Result<Vec<_>, _>>()?;
+                TreeChoices { combat_style, choices: to_array(choices)? }
+            }
+        };
+        discipline.validate(&choices.choices, data)?;
+        Ok(choices)
+    }
+
+    /// The build with option names instead of letters, which `from_code` reads back
+    pub fn name_code(&self, data: &GameData) -> Result<String, BuildCodeError> {
+        let discipline = Discipline::load(&self.combat_style, data)?;
+        let names = discipline.chosen(&self.choices)?
+            .into_iter()
+            .map(|id| data.name(id).map(str::to_string).ok_or(DisciplineError::UnknownNode { discipline: discipline.fqn.clone(), id }))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(format!("{}:{}", self.combat_style.to_string().to_lowercase(), names.join(",")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs_system::get_abilities;
+    use crate::game_data::tests::{field, node};
+    use crate::parse::{ClassView, Field, Id, Int, List, LookupList};
+    use crate::stb::{read_stb, Strings, LOC_NAME};
+    use crate::stb::tests::{retriever, write_stb};
+    use TreeChoice::*;
+
+    fn discipline() -> Discipline {
+        Discipline {
+            fqn: "dis.assassin.darkness".to_string(),
+            rows: vec![
+                Row { level: 15, options: [11, 12, 13] },
+                Row { level: 23, options: [21, 22, 23] },
+            ],
+            baseline: vec![
+                Baseline { level: 1, ability: 1, passive: false },
+                Baseline { level: 20, ability: 2, passive: true },
+            ],
+        }
+    }
+
+    #[test]
+    fn builds() -> Result<(), Box<dyn std::error::Error>> {
+        let dis = discipline();
+        assert_eq!(vec![13, 21], dis.chosen(&[Right, Left])?);
+        assert!(matches!(dis.chosen(&[Right]), Err(DisciplineError::ChoiceCount { expected: 2, found: 1 })));
+        assert_eq!(vec![1, 13], dis.abilities(&[Right, Left], 15)?);
+        assert_eq!(vec![1, 2, 13, 21], dis.abilities(&[Right, Left], 80)?);
+
+        let data = GameData::from_nodes(vec![
+            node(11, "abl.assassin.a", vec![]), node(12, "abl.assassin.b", vec![]),
+            node(13, "tal.assassin.c", vec![]), node(21, "abl.assassin.d", vec![]),
+        ])?;
+        assert!(dis.validate(&[Right, Left], &data).is_ok());
+        assert!(matches!(dis.validate(&[Right, Middle], &data), Err(DisciplineError::UnknownNode { id: 22, .. })));
+        assert_eq!(vec![
+            "row 1 (level 15): abl.assassin.a / abl.assassin.b / [tal.assassin.c]",
+            "row 2 (level 23): [abl.assassin.d] / 22 / 23",
+        ], dis.describe(Some(&[Right, Left]), &data));
+        assert_eq!("row 1 (level 15): abl.assassin.a / abl.assassin.b / tal.assassin.c", dis.describe(None, &data)[0]);
+        Ok(())
+    }
+
+    /// Darkness node with `rows` by level and disModAbilities keys, `mods` from key to option node
+    fn dis_node(rows: &[(i64, &[i64])], mods: &[(i64, u64)]) -> NodeObjPair {
+        let int = |n| FieldValue::Int(Int(n));
+        let mods = mods.iter().map(|&(key, id)| (int(key), FieldValue::Id(Id(id))));
+        let rows = rows.iter().map(|&(level, keys)| (int(level), FieldValue::List(List(keys.iter().map(|&key| int(key)).collect()))));
+        node(16141067504602942620, "dis.assassin.darkness", vec![
+            field(DIS_LEVEL_MOD_LIST, FieldValue::LookupList(LookupList(rows.collect()))),
+            field(DIS_MOD_ABILITIES, FieldValue::LookupList(LookupList(mods.collect()))),
+        ])
+    }
+
+    /// Darkness apc node with `discipline()`'s baseline, ability 1 at level 1 and talent 2 at level 20
+    fn apc_node() -> NodeObjPair {
+        let granted = |id, level_id, level| {
+            let level = Field { id: Id(level_id), value: FieldValue::Int(Int(level)) };
+            FieldValue::LookupList(LookupList(vec![(FieldValue::Id(Id(id)), FieldValue::ClassView(ClassView(vec![level])))]))
+        };
+        node(3, "apc.sith_inquisitor.assassin.darkness", vec![
+            Field { id: Id(APC_ABILITIES_ID), value: granted(1, APC_ABILITY_LEVEL_ID, 1) },
+            Field { id: Id(APC_TALENTS_ID), value: granted(2, APC_TALENT_LEVEL_ID, 20) },
+        ])
+    }
+
+    static MODS: &[(i64, u64)] = &[(1, 11), (2, 12), (3, 13), (4, 21), (5, 22), (6, 23)];
+
+    #[test]
+    fn from_pair() -> Result<(), DisciplineError> {
+        let nodes = vec![node(1, "abl.assassin.a", vec![]), node(2, "tal.assassin.b", vec![])];
+        let data = GameData::from_nodes([nodes.clone(), vec![apc_node()]].concat()).unwrap();
+        assert_eq!(discipline(), Discipline::from_pair(&dis_node(&[(15, &[1, 2, 3]), (23, &[4, 5, 6])], MODS), &data)?);
+
+        let darkness = dis_node(&[(15, &[1, 2, 3])], MODS);
+        let no_apc = GameData::from_nodes(nodes.clone()).unwrap();
+        assert!(matches!(Discipline::from_pair(&darkness, &no_apc), Err(DisciplineError::NoBaseline(_))));
+        let mut no_talents = apc_node();
+        no_talents.obj.0.pop();
+        let no_talents = GameData::from_nodes([nodes, vec![no_talents]].concat()).unwrap();
+        let missing = Discipline::from_pair(&darkness, &no_talents);
+        assert!(matches!(missing, Err(DisciplineError::Field { err: FieldError::Missing(_), .. })), "{missing:?}");
+
+        let two_options = Discipline::from_pair(&dis_node(&[(15, &[1, 2])], MODS), &data);
+        assert!(matches!(two_options, Err(DisciplineError::Field { err: FieldError::Invalid { .. }, .. })), "{two_options:?}");
+        let unknown_key = Discipline::from_pair(&dis_node(&[(15, &[1, 2, 7])], MODS), &data);
+        assert!(matches!(unknown_key, Err(DisciplineError::Field { err: FieldError::Invalid { .. }, .. })), "{unknown_key:?}");
+        Ok(())
+    }
+
+    #[test]
+    fn build_codes() -> Result<(), BuildCodeError> {
+        let build = TreeChoices::from_str("Darkness: rlrrmllm")?;
+        assert_eq!(CombatStyle::Darkness, build.combat_style);
+        assert_eq!([Right, Left, Right, Right, Middle, Left, Left, Middle], build.choices);
+        assert_eq!("darkness:RLRRMLLM", build.to_string());
+        assert_eq!(build, build.to_string().parse()?);
+
+        assert!(matches!(TreeChoices::from_str("RLRRMLLM"), Err(BuildCodeError::NoCombatStyle(_))));
+        assert!(matches!(TreeChoices::from_str("light:RLRRMLLM"), Err(BuildCodeError::UnknownCombatStyle(_))));
+        assert!(matches!(TreeChoices::from_str("darkness:RLRRXLLM"), Err(BuildCodeError::UnknownChoice('X'))));
+        assert!(matches!(TreeChoices::from_str("darkness:RLR"), Err(BuildCodeError::ChoiceCount { expected: 8, found: 3 })));
+        assert!(matches!(
+            TreeChoices::from_code("darkness:RLRRMLLM", &GameData::default()),
+            Err(BuildCodeError::Discipline(DisciplineError::UnknownDiscipline(_)))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn option_names() -> Result<(), Box<dyn std::error::Error>> {
+        // Eight rows, the option with key n is node 100 + n called "Option n"
+        let keys: Vec<_> = (0..8).map(|row| [1, 2, 3].map(|n| row * 3 + n)).collect();
+        let rows: Vec<_> = keys.iter().zip(0..).map(|(keys, row)| (15 + row * 8, &keys[..])).collect();
+        let mods: Vec<_> = (1..=24).map(|key| (key, 100 + key as u64)).collect();
+        let mut nodes = vec![dis_node(&rows, &mods), apc_node(), node(1, "abl.assassin.a", vec![]), node(2, "tal.assassin.b", vec![])];
+        nodes.extend((1..=24).map(|key| node(100 + key, &format!("abl.assassin.option_{key}"), vec![retriever(&[(LOC_NAME, "str.abl", key)])])));
+        let names: Vec<_> = (1..=24).map(|key| (key, format!("Option {key}"))).collect();
+        let names: Vec<_> = names.iter().map(|(key, name)| (*key, name.as_str())).collect();
+        let mut strings = Strings::default();
+        strings.0.insert("str.abl".to_string(), read_stb(&write_stb(&names))?);
+        let data = GameData::from_nodes(nodes)?.with_strings(strings);
+
+        let build = TreeChoices::from_str("darkness:RLRRMLLM")?;
+        let code = build.name_code(&data)?;
+        assert_eq!("darkness:Option 3,Option 4,Option 9,Option 12,Option 14,Option 16,Option 19,Option 23", code);
+        assert_eq!(build, TreeChoices::from_code(&code, &data)?);
+        assert!(get_abilities(build, &data)?.ends_with(&["Option 23".to_string()]));
+        assert_eq!(build, TreeChoices::from_code("darkness:RLRRMLLM", &data)?);
+        // Names ignore case and spaces after the commas
+        assert_eq!(build, TreeChoices::from_code(&code.to_uppercase().replace(',', ", "), &data)?);
+
+        let wrong_row = code.replace("Option 4", "Option 1");
+        assert!(matches!(TreeChoices::from_code(&wrong_row, &data), Err(BuildCodeError::UnknownName { row: 2, .. })));
+        assert!(matches!(TreeChoices::from_code("darkness:Option 3", &data), Err(BuildCodeError::ChoiceCount { expected: 8, found: 1 })));
+        Ok(())
+    }
+}
@@ -1,12 +1,27 @@
 #![allow(dead_code)]
 
 use bevy::prelude::*;
-use assoc::AssocExt;
-use std::fs::File;
-use std::io::BufReader;
-use crate::parse::{FieldValue, NodeObjPair};
-
-static DATA_PATH: &'static str = "data";
+use crate::discipline::{Discipline, DisciplineError};
+use crate::effect::{ability_effects, Effect, ABL_EFFECT_IDS};
+use crate::game_data::GameData;
+use crate::gom::{FieldError, Fields};
+use crate::parse::NodeObjPair;
+use crate::types::staCombatModeType;
+
+pub static DATA_PATH: &str = "data";
+// Ability fields. Times are float seconds, not TimeIntervals, as jedipedia reads them, and ranges are in tens of meters
+// Ability fields. Times are in seconds and ranges in tens of meters
+static ABL_CASTING_TIME: &str = "ablCastingTime";
+static ABL_CHANNELING_TIME: &str = "ablChannelingTime";
+static ABL_COOLDOWN_TIME: &str = "ablCooldownTime";
+static ABL_ENERGY_COST: &str = "ablEnergyCost";
+static ABL_FORCE_COST: &str = "ablForceCost";
+static ABL_ACTION_POINT_COST: &str = "ablActionPointCost";
+static ABL_MAX_RANGE: &str = "ablMaxRange";
+static ABL_IS_HOSTILE: &str = "ablIsHostile";
+static ABL_COMBAT_MODE: &str = "ablCombatMode";
+static ABL_LINE_OF_SIGHT_CHECK: &str = "ablLineOfSightCheck";
+static ABL_BREAKS_STEALTH: &str = "ablBreaksStealth";
 
 #[derive(Component)]
 enum EntityType {
@@ -40,15 +55,9 @@ struct Passive {
     duration: f64,
 }
 
-#[derive(Component)]
+#[derive(Component, Default)]
 struct Passives(Vec<Passive>);
 
-impl Default for Passives {
-    fn default() -> Self {
-        Self(Default::default())
-    }
-}
-
 #[derive(Bundle)]
 struct PlayerBundle {
     kind: EntityType,
@@ -83,51 +92,55 @@ impl Default for DummyBundle {
 
 // Marker for ability entities
 #[derive(Component)]
-struct Ability;
+pub struct Ability;
 
-#[derive(Component)]
-enum AttackType {
+/// Node id of an ability
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct AbilityId(pub u64);
+
+#[derive(Component, Debug, PartialEq)]
+pub enum AttackType {
     Instant,
     Cast(f64), // Change to duration
     Channel(f64), // Change to duration
 }
 
 // Change to duration
-#[derive(Debug, Component, Reflect)]
+#[derive(Debug, PartialEq, Component, Reflect)]
 pub struct Cooldown(pub f64);
 
 // Might need multiple, at least for % based and integer based resources
 // Make into meaningful unit?
-#[derive(Component)]
+#[derive(Component, Debug, PartialEq)]
 struct EnergyCost(f64);
 
 // Make into meaningful unit?
-#[derive(Component)]
+#[derive(Component, Debug, PartialEq)]
 struct MaxRange(f64);
 
-#[derive(Component)]
+#[derive(Component, Debug, PartialEq)]
 enum TargetType {
     Any,
     Attackable,
 }
 
-#[derive(Component)]
+#[derive(Component, Debug, PartialEq)]
 enum CombatMode {
     Melee,
     Ranged,
 }
 
-#[derive(Component)]
+#[derive(Component, Debug, PartialEq)]
 struct LoSRequired(bool);
 
-#[derive(Component)]
+#[derive(Component, Debug, PartialEq)]
 struct BreaksStealth(bool);
 
 #[derive(Component)]
-struct Effects(Vec<Effect>);
+pub struct Effects(pub Vec<Effect>);
 
 #[derive(Component)]
-struct EffectZero(Effect);
+pub struct EffectZero(pub Effect);
 
 // struct Ability {
 //     name: String,
@@ -146,53 +159,7 @@ struct EffectZero(Effect);
 
 // }
 
-// heh
-struct Effect;
-
-// Effect needs:
-// number: usize
-// is_hidden: bool
-// is_passive: bool
-// stack_limit: usize
-// stack_limit_is_by_caster: bool
-// subeffect_epp_details: ..., sound stuff
-// slot: PassiveType
-// persist_after_death: bool
-// subeffects: Vec<SubEffect>
-// tags: Vec<Tag> or HashMap<Tag, bool>
-// cast+channel+1.5s: Duration
-// conEntitySpec: Effect, self?
-// effAbilitySpec: Ability, owner ability
-// effIsInstant: bool
-// effDescriptionStringId: usize
-
-// SubEffect needs:
-// actions: Vec<EffAct>
-// target_overrides: Vec<EffTargOver>
-// triggers: Vec<EffTrigger>
-// inits: Vec<EffInit>
-// cons:
-// cons_logic:
-
-// EffAct needs:
-// name: Type = { ModifyStat, SpellDamage, ModifyThreat, CallEffect }
-// bool/string/int/float_params: Vec<(String/Enum, T)>
-// function_tags: Vec<Tag>
-// time_interval_params:
-// float/int/id_list_params:
-// tag_exclusions:
-// list_params_mapping:
-
-// EffInit needs same as EffAct except:
-// name: Type = { SetTags, SetDescription, SetPassive, SetPersistsAfterDeath, SetType }
-
-// EffTargOver needs same as EffAct except:
-// name: Type = { TriggerTarget }
-
-// EffTrigger needs same as EffAct except:
-// name: Type = { OnDamageDealt, OnEnterCombat }
-
-#[derive(Debug, strum::Display, strum::EnumString)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, strum::Display, strum::EnumString)]
 #[strum(ascii_case_insensitive)]
 pub enum CombatStyle {
     Darkness,
@@ -242,109 +209,141 @@ impl CombatStyle {
         }
     }
 
-    fn get_dis_fqn(&self) -> String {
+    pub fn get_dis_fqn(&self) -> String {
         let class = self.get_class();
         ["dis", &class.to_string(), &self.to_string()].map(|x| x.to_lowercase()).join(".")
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+/// Written as its first letter in build codes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, strum::Display, strum::EnumString)]
+#[strum(ascii_case_insensitive)]
 pub enum TreeChoice {
+    #[strum(serialize = "L")]
     Left,
+    #[strum(serialize = "M")]
     Middle,
+    #[strum(serialize = "R")]
     Right,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct TreeChoices {
     pub combat_style: CombatStyle,
     pub choices: [TreeChoice; 8],
 }
 
 impl TreeChoices {
-    fn get_mods_list(&self) -> Vec<u64> {
-        let dis_fqn = self.combat_style.get_dis_fqn();
-        let dis_path = format!("{}/dis.json", DATA_PATH);
-        let reader = BufReader::new(File::open(dis_path).expect("should find file"));
-        let dis_classes: Vec<NodeObjPair> = serde_json::from_reader(reader).expect("json should be good");
-        let dis_obj = dis_classes.iter()
-                                 .filter(|pair| pair.node.fqn == dis_fqn)
-                                 .map(|pair| pair.obj.clone())
-                                 .next()
-                                 .expect("dis node should exist");
-        match (&dis_obj.0[8].value, &dis_obj.0[9].value) {
-            (FieldValue::LookupList(lvl_modlist), FieldValue::LookupList(int_to_abl_id)) => {
-                self.choices
-                    .iter()
-                    .map(|choice| *choice as usize)
-                    .enumerate()
-                    .map(|(i, choice_num)| {
-                        match &lvl_modlist[i] {
-                            (FieldValue::Int(_), FieldValue::List(v)) => v[choice_num].clone(),
-                            _ => unimplemented!(),
-                        }
-                    })
-                    // .inspect(|x| println!("{x:?}"))
-                    .map(|int| int_to_abl_id.get(&int).expect("correct dis format"))
-                    .map(|id| match id {
-                        FieldValue::Id(num) => *num,
-                        _ => unimplemented!(),
-                    })
-                    .collect()
-            }
-            _ => unimplemented!(),
-        }
+    fn get_mods_list(&self, data: &GameData) -> Result<Vec<u64>, DisciplineError> {
+        let discipline = Discipline::load(&self.combat_style, data)?;
+        discipline.validate(&self.choices, data)?;
+        discipline.chosen(&self.choices)
     }
 }
 
-pub fn read_dis() {
-    let dis_path = format!("{}/dis.json", DATA_PATH);
-    let reader = BufReader::new(File::open(dis_path).expect("should find file"));
-    let classes: Vec<NodeObjPair> = serde_json::from_reader(reader).expect("json should be good");
-    println!("{:?}", classes[0].obj);
+/// Logs the rows and baseline abilities of a discipline
+pub fn read_dis(combat_style: &CombatStyle, data: &GameData) -> Result<(), DisciplineError> {
+    let discipline = Discipline::load(combat_style, data)?;
+    info!("{}: {:?}", combat_style.get_dis_fqn(), discipline);
+    Ok(())
 }
 
-pub fn get_abilities(choices: TreeChoices) {
-    let mods_ids = choices.get_mods_list();
-    let abl_path = format!("{}/abl.json", DATA_PATH);
-    let reader = BufReader::new(File::open(abl_path).expect("should find file"));
-    let dis_classes: Vec<NodeObjPair> = serde_json::from_reader(reader).expect("json should be good");
-    let abilities: Vec<_> = mods_ids.iter()
-                                    .map(|id| dis_classes.iter()
-                                                         .filter(|pair| &id.to_string() == &pair.node.id)
-                                                         .map(|pair| pair.node.fqn.clone())
-                                                         .next()
-                                                         .or_else(|| get_talent(*id))
-                                                         .unwrap())
-                                    .collect();
-    println!("{:?}", abilities);
+/// Names of the abilities and talents picked in the discipline tree, which are also logged
+pub fn get_abilities(choices: TreeChoices, data: &GameData) -> Result<Vec<String>, DisciplineError> {
+    let abilities: Vec<_> = choices.get_mods_list(data)?
+        .into_iter()
+        .filter_map(|id| {
+            let name = data.name(id);
+            if name.is_none() {
+                warn!("{}: no name for node {id}", choices.combat_style.get_dis_fqn());
+            }
+            name.map(str::to_string)
+        })
+        .collect();
+    info!("{abilities:?}");
+    Ok(abilities)
 }
 
-fn get_talent(id: u64) -> Option<String> {
-    let tal_path = format!("{}/tal.json", DATA_PATH);
-    let reader = BufReader::new(File::open(tal_path).expect("should find file"));
-    let tal_classes: Vec<NodeObjPair> = serde_json::from_reader(reader).expect("json should be good");
-    tal_classes.iter()
-               .filter(|pair| &id.to_string() == &pair.node.id)
-               .map(|pair| pair.node.fqn.clone())
-               .next()
-}
 
+/// Field value, or `default` if it couldn't be used. The reason is added to `problems`
+fn or_report<T>(result: Result<T, FieldError>, default: T, problems: &mut Vec<FieldError>) -> T {
+    result.unwrap_or_else(|err| {
+        problems.push(err);
+        default
+    })
+}
 
-fn create_abilities(mut commands: Commands) {
-    commands.spawn((
+/// Spawns an ability entity from an `abl.*` node.
+/// Fields that are missing or unsupported get a default value and are returned.
+pub fn spawn_ability(commands: &mut Commands, pair: &NodeObjPair, data: &GameData) -> (Entity, Vec<FieldError>) {
+    let obj = &pair.obj;
+    let mut problems = vec![];
+    let mut float = |name| or_report(obj.get_float(name).map(f64::from), 0.0, &mut problems);
+
+    let casting_time = float(ABL_CASTING_TIME);
+    let channeling_time = float(ABL_CHANNELING_TIME);
+    let attack_type = if casting_time > 0.0 {
+        AttackType::Cast(casting_time)
+    } else if channeling_time > 0.0 {
+        AttackType::Channel(channeling_time)
+    } else {
+        AttackType::Instant
+    };
+    let cooldown = float(ABL_COOLDOWN_TIME);
+    // Classes only use one of these
+    let cost = [ABL_ENERGY_COST, ABL_FORCE_COST, ABL_ACTION_POINT_COST]
+        .map(&mut float)
+        .into_iter()
+        .find(|cost| *cost > 0.0)
+        .unwrap_or(0.0);
+    let max_range = float(ABL_MAX_RANGE) * 10.0;
+
+    let target_type = match or_report(obj.get_bool(ABL_IS_HOSTILE), true, &mut problems) {
+        true => TargetType::Attackable,
+        false => TargetType::Any,
+    };
+    // The generated enum can have more variants than we support, so no exhaustive match
+    let combat_mode = obj.get_enum(ABL_COMBAT_MODE).and_then(|value| {
+        let mode = staCombatModeType::try_from(value)
+            .map_err(|value| FieldError::invalid(ABL_COMBAT_MODE, format!("unknown combat mode {value}")))?;
+        if mode == staCombatModeType::staCombatModeMelee {
+            Ok(CombatMode::Melee)
+        } else if mode == staCombatModeType::staCombatModeRanged {
+            Ok(CombatMode::Ranged)
+        } else {
+            Err(FieldError::invalid(ABL_COMBAT_MODE, format!("unsupported combat mode {mode}")))
+        }
+    });
+    let combat_mode = or_report(combat_mode, CombatMode::Ranged, &mut problems);
+    let los_required = or_report(obj.get_bool(ABL_LINE_OF_SIGHT_CHECK), true, &mut problems);
+    let breaks_stealth = or_report(obj.get_bool(ABL_BREAKS_STEALTH), true, &mut problems);
+    // Not a missing field with a default, so never reported as one
+    let effects = ability_effects(obj, data)
+        .map_err(|err| FieldError::invalid(ABL_EFFECT_IDS, format!("effects not loaded, {err}")));
+    let (effects, effect_zero) = or_report(effects, (vec![], None), &mut problems);
+    // The fqn until strings are loaded
+    let name = data.strings().name(obj).unwrap_or(&pair.node.fqn);
+
+    let mut entity = commands.spawn((
         Ability,
-        Name::new("Rail shot"),
-        AttackType::Instant,
-        Cooldown(15.0),
-        EnergyCost(15.0),
-        MaxRange(10.0),
-        TargetType::Attackable,
-        CombatMode::Ranged,
-        LoSRequired(true),
-        BreaksStealth(true),
-        Effects(vec![]),
-        EffectZero(Effect {})
+        Name::new(name.to_string()),
+        attack_type,
+        Cooldown(cooldown),
+        EnergyCost(cost),
+        MaxRange(max_range),
+        target_type,
+        combat_mode,
+        LoSRequired(los_required),
+        BreaksStealth(breaks_stealth),
+        Effects(effects),
     ));
+    if let Some(effect) = effect_zero {
+        entity.insert(EffectZero(effect));
+    }
+    if let Ok(id) = pair.node.id.parse() {
+        entity.insert(AbilityId(id));
+    }
+    (entity.id(), problems)
 }
 
 fn do_stuff(entity: Entity, world: &World) {
@@ -363,3 +362,53 @@ fn do_stuff(entity: Entity, world: &World) {
         println!("Cooldown: {cooldown}");
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::ecs::system::CommandQueue;
+    use crate::game_data::tests::node;
+    use crate::stb::{read_stb, Strings, LOC_NAME};
+    use crate::stb::tests::{retriever, write_stb};
+
+    #[test]
+    fn spawn_ability_defaults() {
+        let pair = node(1, "abl.test.empty", vec![]);
+        let mut world = World::new();
+        let mut queue = CommandQueue::default();
+        let (entity, problems) = spawn_ability(&mut Commands::new(&mut queue, &world), &pair, &GameData::default());
+        queue.apply(&mut world);
+
+        // Every component field is reported as missing, no effects is fine
+        let missing = [
+            ABL_CASTING_TIME, ABL_CHANNELING_TIME, ABL_COOLDOWN_TIME, ABL_ENERGY_COST, ABL_FORCE_COST,
+            ABL_ACTION_POINT_COST, ABL_MAX_RANGE, ABL_IS_HOSTILE, ABL_COMBAT_MODE, ABL_LINE_OF_SIGHT_CHECK,
+            ABL_BREAKS_STEALTH,
+        ];
+        assert_eq!(missing.map(|name| FieldError::Missing(name.to_string())).to_vec(), problems);
+        let ability = world.entity(entity);
+        assert_eq!("abl.test.empty", ability.get::<Name>().unwrap().as_str());
+        assert_eq!(Some(&AttackType::Instant), ability.get::<AttackType>());
+        assert_eq!(Some(&Cooldown(0.0)), ability.get::<Cooldown>());
+        assert_eq!(Some(&TargetType::Attackable), ability.get::<TargetType>());
+        assert_eq!(Some(&LoSRequired(true)), ability.get::<LoSRequired>());
+        assert!(ability.get::<Effects>().unwrap().0.is_empty());
+        assert!(!ability.contains::<EffectZero>());
+        assert_eq!(Some(&AbilityId(1)), ability.get::<AbilityId>());
+    }
+
+    #[test]
+    fn spawn_ability_name() -> Result<(), Box<dyn std::error::Error>> {
+        let pair = node(1, "abl.test.rail_shot", vec![retriever(&[(LOC_NAME, "str.abl", 7)])]);
+        let mut strings = Strings::default();
+        strings.0.insert("str.abl".to_string(), read_stb(&write_stb(&[(7, "Rail Shot")]))?);
+        let data = GameData::from_nodes(vec![pair.clone()])?.with_strings(strings);
+        let mut world = World::new();
+        let mut queue = CommandQueue::default();
+        let (entity, _) = spawn_ability(&mut Commands::new(&mut queue, &world), &pair, &data);
+        queue.apply(&mut world);
+        assert_eq!("Rail Shot", world.entity(entity).get::<Name>().unwrap().as_str());
+        assert_eq!(Some("Rail Shot"), data.name(1));
+        Ok(())
+    }
+}
@@ -0,0 +1,453 @@
+#![allow(dead_code)]
+
+// Ability effects, read from the effect nodes an ability references.
+// An effect is a list of subeffects. Each subeffect has the actions done when it's applied,
+// plus initializers, target overrides and triggers, which are laid out like actions.
+
+use std::str::FromStr;
+use std::time::Duration;
+use crate::game_data::GameData;
+use crate::gom::{default_if_missing, FieldError, Fields};
+use crate::parse::{ClassView, FieldValue, Id, Int, NodeRef};
+use crate::types::{variant_name, variant_value};
+
+pub(crate) static ABL_EFFECT_IDS: &str = "ablEffectIDs";
+static ABL_EFFECT_ZERO: &str = "ablEffectZero";
+
+pub(crate) static EFF_ID: &str = "effId";
+static EFF_IS_HIDDEN: &str = "effIsHidden";
+static EFF_IS_PASSIVE: &str = "effIsPassive";
+static EFF_STACK_LIMIT: &str = "effStackLimit";
+static EFF_STACK_LIMIT_IS_BY_CASTER: &str = "effStackLimitIsByCaster";
+static EFF_SLOT: &str = "effSlot";
+static EFF_PERSIST_AFTER_DEATH: &str = "effPersistAfterDeath";
+static EFF_TAGS: &str = "effTags";
+pub(crate) static EFF_DURATION: &str = "effDuration";
+static EFF_SUB_EFFECTS: &str = "effSubEffects";
+
+static EFF_ACTIONS: &str = "effActions";
+static EFF_TARGET_OVERRIDES: &str = "effTargetOverrides";
+static EFF_TRIGGERS: &str = "effTriggers";
+static EFF_INITIALIZERS: &str = "effInitializers";
+
+static EFF_ACTION_NAME: &str = "effActionName";
+static EFF_PARAMS: &str = "effParams";
+static EFF_FUNCTION_TAGS: &str = "effFunctionTags";
+static EFF_TAG_EXCLUSIONS: &str = "effTagExclusions";
+/// GOM enum of the keys of `effParams`
+static EFF_PARAM_TYPE: &str = "effParamType";
+
+/// Name of a GOM enum, the variant names of which parse into `Self`
+pub trait GomName: FromStr {
+    const ENUM: &'static str;
+}
+
+#[derive(Debug, Clone, PartialEq, strum::EnumString)]
+pub enum ActionName {
+    ModifyStat,
+    SpellDamage,
+    ModifyThreat,
+    CallEffect,
+    #[strum(default)]
+    Other(String),
+}
+
+impl GomName for ActionName {
+    const ENUM: &'static str = "effActionType";
+}
+
+#[derive(Debug, Clone, PartialEq, strum::EnumString)]
+pub enum InitName {
+    SetTags,
+    SetDescription,
+    SetPassive,
+    SetPersistsAfterDeath,
+    SetType,
+    #[strum(default)]
+    Other(String),
+}
+
+impl GomName for InitName {
+    const ENUM: &'static str = "effInitializerType";
+}
+
+#[derive(Debug, Clone, PartialEq, strum::EnumString)]
+pub enum TargetOverrideName {
+    TriggerTarget,
+    #[strum(default)]
+    Other(String),
+}
+
+impl GomName for TargetOverrideName {
+    const ENUM: &'static str = "effTargetOverrideType";
+}
+
+#[derive(Debug, Clone, PartialEq, strum::EnumString)]
+pub enum TriggerName {
+    OnDamageDealt,
+    OnEnterCombat,
+    OnAbilityActivate,
+    OnEffectApplied,
+    #[strum(default)]
+    Other(String),
+}
+
+impl GomName for TriggerName {
+    const ENUM: &'static str = "effTriggerType";
+}
+
+#[derive(Debug, Clone, Default, PartialEq, strum::EnumString)]
+pub enum EffectSlot {
+    #[default]
+    None,
+    Buff,
+    Debuff,
+    #[strum(default)]
+    Other(String),
+}
+
+impl GomName for EffectSlot {
+    const ENUM: &'static str = "effSlotType";
+}
+
+/// Numeric value of something used as a key or tag
+pub fn key_of(value: &FieldValue) -> Option<u64> {
+    match value {
+        FieldValue::Id(Id(id)) | FieldValue::Enum(Id(id)) | FieldValue::NodeRef(NodeRef(Id(id))) => Some(*id),
+        FieldValue::Int(Int(int)) => u64::try_from(*int).ok(),
+        _ => None,
+    }
+}
+
+/// Variant of a GOM enum field, parsed into `N`
+pub fn gom_name<N: GomName, F: Fields + ?Sized>(obj: &F, field: &str) -> Result<N, FieldError> {
+    let value = obj.get_enum(field)?;
+    let name = variant_name(N::ENUM, value)
+        .ok_or_else(|| FieldError::invalid(field, format!("{value} isn't in enum {}", N::ENUM)))?;
+    N::from_str(name).map_err(|_| FieldError::invalid(field, format!("can't parse {name}")))
+}
+
+fn key_list<F: Fields + ?Sized>(obj: &F, field: &str) -> Result<Vec<u64>, FieldError> {
+    default_if_missing(obj.get_list(field))?
+        .iter()
+        .enumerate()
+        .map(|(i, value)| key_of(value).ok_or_else(|| FieldError::WrongType {
+            field: format!("{field}[{i}]"),
+            expected: "Id",
+            found: value.type_name(),
+        }))
+        .collect()
+}
+
+/// Parses every ClassView in a list, missing lists are empty
+fn class_list<T, F: Fields + ?Sized>(obj: &F, field: &str, parse: impl Fn(&ClassView) -> Result<T, FieldError>) -> Result<Vec<T>, FieldError> {
+    default_if_missing(obj.get_list(field))?
+        .iter()
+        .enumerate()
+        .map(|(i, value)| match value {
+            FieldValue::ClassView(class) => parse(class).map_err(|e| e.within(&format!("{field}[{i}]"))),
+            other => Err(FieldError::WrongType { field: format!("{field}[{i}]"), expected: "ClassView", found: other.type_name() }),
+        })
+        .collect()
+}
+
+fn find<T: Copy>(params: &[(u64, T)], key: u64) -> Option<T> {
+    params.iter().find(|(k, _)| *k == key).map(|(_, v)| *v)
+}
+
+/// Parameters by their key, split up by value type
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Params {
+    pub bools: Vec<(u64, bool)>,
+    pub ints: Vec<(u64, i64)>,
+    pub floats: Vec<(u64, f32)>,
+    pub ids: Vec<(u64, u64)>,
+    pub strings: Vec<(u64, String)>,
+    pub time_intervals: Vec<(u64, Duration)>,
+    pub int_lists: Vec<(u64, Vec<i64>)>,
+    pub float_lists: Vec<(u64, Vec<f32>)>,
+    pub id_lists: Vec<(u64, Vec<u64>)>,
+    /// Anything else, including empty lists since their type is unknown
+    pub other: Vec<(u64, FieldValue)>,
+}
+
+/// Key of a parameter by its name
+pub fn param_key(name: &str) -> Result<u64, FieldError> {
+    variant_value(EFF_PARAM_TYPE, name)
+        .ok_or_else(|| FieldError::invalid(EFF_PARAMS, format!("{name} isn't in enum {EFF_PARAM_TYPE}")))
+}
+
+impl Params {
+    pub fn from_lookup(entries: &[(FieldValue, FieldValue)]) -> Result<Self, FieldError> {
+        let mut params = Params::default();
+        for (i, (key, value)) in entries.iter().enumerate() {
+            let key = key_of(key).ok_or_else(|| FieldError::invalid(EFF_PARAMS, format!("[{i}] has key {key:?}")))?;
+            match value {
+                FieldValue::Boolean(b) => params.bools.push((key, *b)),
+                FieldValue::Int(Int(int)) => params.ints.push((key, *int)),
+                FieldValue::Float(float) => params.floats.push((key, *float)),
+                FieldValue::Id(Id(id)) | FieldValue::Enum(Id(id)) | FieldValue::NodeRef(NodeRef(Id(id))) => params.ids.push((key, *id)),
+                FieldValue::String(s) => params.strings.push((key, s.clone())),
+                FieldValue::TimeInterval(time) => params.time_intervals.push((key, *time)),
+                FieldValue::List(list) => match list.0.first() {
+                    Some(FieldValue::Int(_)) => params.int_lists.push((key, list.0.iter().filter_map(FieldValue::as_int).collect())),
+                    Some(FieldValue::Float(_)) => params.float_lists.push((key, list.0.iter().filter_map(|v| match v {
+                        FieldValue::Float(f) => Some(*f),
+                        _ => None,
+                    }).collect())),
+                    Some(first) if key_of(first).is_some() => params.id_lists.push((key, list.0.iter().filter_map(key_of).collect())),
+                    _ => params.other.push((key, value.clone())),
+                },
+                _ => params.other.push((key, value.clone())),
+            }
+        }
+        Ok(params)
+    }
+
+    pub fn bool(&self, key: u64) -> Option<bool> {
+        find(&self.bools, key)
+    }
+
+    pub fn int(&self, key: u64) -> Option<i64> {
+        find(&self.ints, key)
+    }
+
+    pub fn float(&self, key: u64) -> Option<f32> {
+        find(&self.floats, key)
+    }
+
+    pub fn id(&self, key: u64) -> Option<u64> {
+        find(&self.ids, key)
+    }
+
+    pub fn time_interval(&self, key: u64) -> Option<Duration> {
+        find(&self.time_intervals, key)
+    }
+}
+
+/// An action, or an initializer, target override or trigger depending on `N`
+#[derive(Debug, Clone, PartialEq)]
+pub struct EffAct<N> {
+    pub name: N,
+    pub params: Params,
+    pub function_tags: Vec<u64>,
+    pub tag_exclusions: Vec<u64>,
+}
+
+pub type EffInit = EffAct<InitName>;
+pub type EffTargOver = EffAct<TargetOverrideName>;
+pub type EffTrigger = EffAct<TriggerName>;
+
+impl<N: GomName> EffAct<N> {
+    pub fn from_fields<F: Fields + ?Sized>(obj: &F) -> Result<Self, FieldError> {
+        Ok(EffAct {
+            name: gom_name(obj, EFF_ACTION_NAME)?,
+            params: Params::from_lookup(default_if_missing(obj.get_lookup(EFF_PARAMS))?)?,
+            function_tags: key_list(obj, EFF_FUNCTION_TAGS)?,
+            tag_exclusions: key_list(obj, EFF_TAG_EXCLUSIONS)?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SubEffect {
+    pub actions: Vec<EffAct<ActionName>>,
+    pub target_overrides: Vec<EffTargOver>,
+    pub triggers: Vec<EffTrigger>,
+    pub inits: Vec<EffInit>,
+}
+
+impl SubEffect {
+    pub fn from_fields<F: Fields + ?Sized>(obj: &F) -> Result<Self, FieldError> {
+        Ok(SubEffect {
+            actions: class_list(obj, EFF_ACTIONS, EffAct::from_fields)?,
+            target_overrides: class_list(obj, EFF_TARGET_OVERRIDES, EffAct::from_fields)?,
+            triggers: class_list(obj, EFF_TRIGGERS, EffAct::from_fields)?,
+            inits: class_list(obj, EFF_INITIALIZERS, EffAct::from_fields)?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Effect {
+    pub id: u64,
+    pub is_hidden: bool,
+    pub is_passive: bool,
+    /// 0 if it doesn't stack
+    pub stack_limit: u32,
+    pub stack_limit_is_by_caster: bool,
+    pub slot: EffectSlot,
+    pub persist_after_death: bool,
+    pub tags: Vec<u64>,
+    pub duration: Duration,
+    pub sub_effects: Vec<SubEffect>,
+}
+
+impl Effect {
+    pub fn from_fields<F: Fields + ?Sized>(obj: &F) -> Result<Self, FieldError> {
+        let stack_limit = default_if_missing(obj.get_int(EFF_STACK_LIMIT))?;
+        let slot = match gom_name(obj, EFF_SLOT) {
+            Err(FieldError::Missing(_)) => EffectSlot::None,
+            slot => slot?,
+        };
+        Ok(Effect {
+            id: obj.get_id(EFF_ID)?,
+            is_hidden: default_if_missing(obj.get_bool(EFF_IS_HIDDEN))?,
+            is_passive: default_if_missing(obj.get_bool(EFF_IS_PASSIVE))?,
+            stack_limit: u32::try_from(stack_limit)
+                .map_err(|_| FieldError::invalid(EFF_STACK_LIMIT, format!("{stack_limit} is out of range")))?,
+            stack_limit_is_by_caster: default_if_missing(obj.get_bool(EFF_STACK_LIMIT_IS_BY_CASTER))?,
+            slot,
+            persist_after_death: default_if_missing(obj.get_bool(EFF_PERSIST_AFTER_DEATH))?,
+            tags: key_list(obj, EFF_TAGS)?,
+            duration: default_if_missing(obj.get_time(EFF_DURATION))?,
+            sub_effects: class_list(obj, EFF_SUB_EFFECTS, SubEffect::from_fields)?,
+        })
+    }
+
+    /// Reads the effect node `node_ref` points to, `field` is where the reference came from
+    pub fn resolve(node_ref: NodeRef, data: &GameData, field: &str) -> Result<Self, FieldError> {
+        Self::load(node_ref.0.0, data, field)
+    }
+
+    /// Reads the effect with this effId or node id, see [`GameData::effect`]
+    pub fn load(id: u64, data: &GameData, field: &str) -> Result<Self, FieldError> {
+        let pair = data.effect(id)
+            .ok_or_else(|| FieldError::invalid(field, format!("effect {id} doesn't exist")))?;
+        Effect::from_fields(&pair.obj).map_err(|e| e.within(&pair.node.fqn))
+    }
+}
+
+/// The effects of an ability and its effect zero, which is applied before the others
+pub fn ability_effects<F: Fields + ?Sized>(ability: &F, data: &GameData) -> Result<(Vec<Effect>, Option<Effect>), FieldError> {
+    let effects = key_list(ability, ABL_EFFECT_IDS)?
+        .into_iter()
+        .map(|id| Effect::load(id, data, ABL_EFFECT_IDS))
+        .collect::<Result<_, _>>()?;
+    let effect_zero = match ability.get_node_ref(ABL_EFFECT_ZERO) {
+        Err(FieldError::Missing(_)) => None,
+        node_ref => Some(Effect::resolve(node_ref?, data, ABL_EFFECT_ZERO)?),
+    };
+    Ok((effects, effect_zero))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::game_data::EFFECT_BASE_CLASS;
+    use crate::game_data::tests::{field as gom_field, node};
+    use crate::parse::{List, LookupList, NodeObjPair};
+
+    /// Effect node with one subeffect running these (action name, params by name) actions.
+    /// Its effId is the node id
+    pub(crate) fn effect_node(id: u64, actions: &[(&str, Vec<(&str, FieldValue)>)]) -> NodeObjPair {
+        let actions = actions.iter().map(|(name, params)| {
+            let params = params.iter()
+                .map(|(key, value)| (FieldValue::Enum(Id(param_key(key).expect("effParamType variant"))), value.clone()))
+                .collect();
+            FieldValue::ClassView(ClassView(vec![
+                gom_field(EFF_ACTION_NAME, FieldValue::Enum(Id(variant_value(ActionName::ENUM, name).expect("effActionType variant")))),
+                gom_field(EFF_PARAMS, FieldValue::LookupList(LookupList(params))),
+            ]))
+        }).collect();
+        let sub_effect = ClassView(vec![gom_field(EFF_ACTIONS, FieldValue::List(List(actions)))]);
+        let mut pair = node(id, &format!("abl.test.effect_{id}"), vec![
+            gom_field(EFF_ID, FieldValue::Id(Id(id))),
+            gom_field(EFF_SUB_EFFECTS, FieldValue::List(List(vec![FieldValue::ClassView(sub_effect)]))),
+        ]);
+        pair.node.base_class = EFFECT_BASE_CLASS.to_string();
+        pair
+    }
+
+    #[test]
+    fn params() -> Result<(), FieldError> {
+        let entries = vec![
+            (FieldValue::Enum(Id(1)), FieldValue::Boolean(true)),
+            (FieldValue::Enum(Id(2)), FieldValue::Float(0.5)),
+            (FieldValue::Int(Int(3)), FieldValue::List(List(vec![FieldValue::Id(Id(7)), FieldValue::Id(Id(8))]))),
+            (FieldValue::Enum(Id(4)), FieldValue::List(List(vec![]))),
+            (FieldValue::Enum(Id(5)), FieldValue::TimeInterval(Duration::from_millis(1500))),
+        ];
+        let params = Params::from_lookup(&entries)?;
+        assert_eq!(Some(true), params.bool(1));
+        assert_eq!(Some(0.5), params.float(2));
+        assert_eq!(None, params.float(1));
+        assert_eq!(vec![(3, vec![7, 8])], params.id_lists);
+        assert_eq!(vec![(4, FieldValue::List(List(vec![])))], params.other);
+        assert_eq!(Some(Duration::from_millis(1500)), params.time_interval(5));
+
+        let bad_key = vec![(FieldValue::Float(1.0), FieldValue::Boolean(true))];
+        assert!(Params::from_lookup(&bad_key).is_err());
+        Ok(())
+    }
+
+    fn field(name: &str, value_type: u8, value: serde_json::Value) -> serde_json::Value {
+        let id = crate::gom::field_id(name).expect("GOM field").to_string();
+        serde_json::json!({ "id": id, "type": value_type, "value": value })
+    }
+
+    /// Same as [`field`] inside a ClassView
+    fn val(name: &str, value_type: u8, value: serde_json::Value) -> serde_json::Value {
+        let mut json = field(name, value_type, value);
+        let value = json.as_object_mut().unwrap().remove("value").unwrap();
+        json["val"] = value;
+        json
+    }
+
+    fn big_int(value: u64) -> serde_json::Value {
+        serde_json::json!({ "sign": 1, "intLo": value as u32, "intHi": (value >> 32) as u32, "len": 9 })
+    }
+
+    #[test]
+    fn ability_effects_from_nodes() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::game_data::{ABILITY_BASE_CLASS, EFFECT_BASE_CLASS};
+        use crate::parse::NodeObjPair;
+
+        // Abilities list effIds, which aren't the effect's node id
+        let eff_id = 16141176512342836547;
+        let spell_damage = variant_value(ActionName::ENUM, "SpellDamage").expect("effActionType variant");
+        let coefficient = param_key("Coefficient")?;
+        let nodes: Vec<NodeObjPair> = serde_json::from_value(serde_json::json!([
+            {
+                "node": { "id": "16140902893827567561", "fqn": "abl.test.rail_shot", "path": "", "fileName": "", "baseClass": ABILITY_BASE_CLASS },
+                "obj": [field(ABL_EFFECT_IDS, 7, serde_json::json!({ "type": 1, "list": [big_int(eff_id)] }))],
+            },
+            {
+                "node": { "id": "16140902893827567562", "fqn": "abl.test.rail_shot.damage", "path": "", "fileName": "", "baseClass": EFFECT_BASE_CLASS },
+                "obj": [
+                    field(EFF_ID, 1, big_int(eff_id)),
+                    field(EFF_DURATION, 20, serde_json::json!(3000)),
+                    field(EFF_SUB_EFFECTS, 7, serde_json::json!({ "type": 9, "list": [[
+                        val(EFF_ACTIONS, 7, serde_json::json!({ "type": 9, "list": [[
+                            val(EFF_ACTION_NAME, 5, serde_json::json!(spell_damage)),
+                            val(EFF_PARAMS, 8, serde_json::json!({
+                                "indexType": 5, "type": 4, "list": [{ "key": coefficient, "val": 1.5 }],
+                            })),
+                        ]]})),
+                    ]]})),
+                ],
+            },
+        ]))?;
+        let data = GameData::from_nodes(nodes)?;
+        assert_eq!(vec!["abl.test.rail_shot"], data.abilities().map(|pair| pair.node.fqn.as_str()).collect::<Vec<_>>());
+
+        let effect = Effect::from_fields(&data.get_by_fqn("abl.test.rail_shot.damage").unwrap().obj)?;
+        assert_eq!(eff_id, effect.id);
+        assert_eq!(Duration::from_secs(3), effect.duration);
+        let action = &effect.sub_effects[0].actions[0];
+        assert_eq!(ActionName::SpellDamage, action.name);
+        assert_eq!(Some(1.5), action.params.float(coefficient));
+
+        let (effects, effect_zero) = ability_effects(&data.get_by_fqn("abl.test.rail_shot").unwrap().obj, &data)?;
+        assert_eq!(vec![effect], effects);
+        assert_eq!(None, effect_zero);
+        assert!(matches!(Effect::load(1, &data, ABL_EFFECT_IDS), Err(FieldError::Invalid { .. })));
+        Ok(())
+    }
+
+    #[test]
+    fn names() {
+        assert_eq!(Ok(ActionName::SpellDamage), ActionName::from_str("SpellDamage"));
+        assert_eq!(Ok(TriggerName::Other("OnHeal".to_string())), TriggerName::from_str("OnHeal"));
+    }
+}
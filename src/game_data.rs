@@ -0,0 +1,263 @@
+#![allow(dead_code)]
+
+// All nodes the simulator uses and their strings, loaded once and indexed by id and fqn.
+// Normally they come from the cache build.rs embeds, data/*.json is only needed to build.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufReader};
+use std::path::Path;
+use bevy::prelude::*;
+use crate::archive::Archives;
+use crate::cache::{self, CacheData, CacheError, NODE_PREFIXES, STRINGS_FILE};
+use crate::effect::EFF_ID;
+use crate::gom::Fields;
+use crate::node::{self, NodeError};
+use crate::parse::{NodeObjPair, NodeRef};
+use crate::stb::Strings;
+
+static BUILTIN_CACHE: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/game_data.bin"));
+/// Abilities and effects are both abl nodes, told apart by base class like `node_abl`
+/// and `getEffectCustomActions` in jedipedia.js do
+pub static ABILITY_BASE_CLASS: &str = "4611686018469590226";
+pub static EFFECT_BASE_CLASS: &str = "4611686018468630240";
+
+#[derive(Debug)]
+pub enum GameDataError {
+    Io { file: String, err: io::Error },
+    Json { file: String, err: serde_json::Error },
+    Cache(CacheError),
+    Node(NodeError),
+    BadId { fqn: String, id: String },
+}
+
+impl fmt::Display for GameDataError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GameDataError::Io { file, err } => write!(f, "{file}: {err}"),
+            GameDataError::Json { file, err } => write!(f, "{file}: {err}"),
+            GameDataError::Cache(err) => write!(f, "{err}"),
+            GameDataError::Node(err) => write!(f, "{err}"),
+            GameDataError::BadId { fqn, id } => write!(f, "{fqn}: node id {id} is not a number"),
+        }
+    }
+}
+
+impl std::error::Error for GameDataError {}
+
+impl From<CacheError> for GameDataError {
+    fn from(value: CacheError) -> Self {
+        GameDataError::Cache(value)
+    }
+}
+
+impl From<NodeError> for GameDataError {
+    fn from(value: NodeError) -> Self {
+        GameDataError::Node(value)
+    }
+}
+
+#[derive(Resource, Default, Debug, Clone)]
+pub struct GameData {
+    nodes: Vec<NodeObjPair>,
+    by_id: HashMap<u64, usize>,
+    by_fqn: HashMap<String, usize>,
+    /// Effect nodes by their effId
+    effects: HashMap<u64, usize>,
+    /// Names and descriptions
+    strings: Strings,
+}
+
+impl GameData {
+    /// The data compiled into the binary at build time
+    pub fn builtin() -> Result<Self, GameDataError> {
+        Self::from_cache(BUILTIN_CACHE)
+    }
+
+    pub fn from_cache(bytes: &[u8]) -> Result<Self, GameDataError> {
+        let data: CacheData = cache::decode(bytes)?;
+        Ok(Self::from_nodes(data.nodes)?.with_strings(Strings::from_map(data.strings)))
+    }
+
+    /// Reads `{prefix}.json` for every prefix in [`NODE_PREFIXES`] and [`STRINGS_FILE`],
+    /// skipping files that don't exist
+    pub fn load<P: AsRef<Path>>(data_path: P) -> Result<Self, GameDataError> {
+        let mut nodes = vec![];
+        for prefix in NODE_PREFIXES {
+            let Some(pairs) = read_json::<Vec<NodeObjPair>>(&data_path.as_ref().join(format!("{prefix}.json")))? else {
+                warn!("{prefix}.json not found, no {prefix} nodes loaded");
+                continue;
+            };
+            nodes.extend(pairs);
+        }
+        let strings = read_json(&data_path.as_ref().join(STRINGS_FILE))?.unwrap_or_default();
+        Ok(Self::from_nodes(nodes)?.with_strings(Strings::from_map(strings)))
+    }
+
+    /// Reads the nodes of every kind in [`NODE_PREFIXES`] straight from the client's buckets
+    pub fn from_archives(archives: &Archives) -> Result<Self, GameDataError> {
+        Self::from_nodes(node::read_nodes(archives, &NODE_PREFIXES)?)
+    }
+
+    pub fn from_nodes(nodes: Vec<NodeObjPair>) -> Result<Self, GameDataError> {
+        let mut by_id = HashMap::with_capacity(nodes.len());
+        let mut by_fqn = HashMap::with_capacity(nodes.len());
+        let mut effects = HashMap::new();
+        for (i, pair) in nodes.iter().enumerate() {
+            let id = pair.node.id.parse().map_err(|_| GameDataError::BadId {
+                fqn: pair.node.fqn.clone(),
+                id: pair.node.id.clone(),
+            })?;
+            by_id.insert(id, i);
+            by_fqn.insert(pair.node.fqn.clone(), i);
+            if pair.node.base_class == EFFECT_BASE_CLASS {
+                if let Ok(effect_id) = pair.obj.get_id(EFF_ID) {
+                    effects.insert(effect_id, i);
+                }
+            }
+        }
+        Ok(GameData { nodes, by_id, by_fqn, effects, strings: Strings::default() })
+    }
+
+    pub fn with_strings(mut self, strings: Strings) -> Self {
+        self.strings = strings;
+        self
+    }
+
+    pub fn strings(&self) -> &Strings {
+        &self.strings
+    }
+
+    /// Localized name of a node, or its fqn if it has none
+    pub fn name(&self, id: u64) -> Option<&str> {
+        let pair = self.get(id)?;
+        Some(self.strings.name(&pair.obj).unwrap_or(&pair.node.fqn))
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn get(&self, id: u64) -> Option<&NodeObjPair> {
+        self.by_id.get(&id).map(|&i| &self.nodes[i])
+    }
+
+    pub fn get_by_fqn(&self, fqn: &str) -> Option<&NodeObjPair> {
+        self.by_fqn.get(fqn).map(|&i| &self.nodes[i])
+    }
+
+    pub fn resolve(&self, node_ref: NodeRef) -> Option<&NodeObjPair> {
+        self.get(node_ref.0.0)
+    }
+
+    /// Effect node by its effId, which is what abilities list in ablEffectIDs, or by node id
+    pub fn effect(&self, id: u64) -> Option<&NodeObjPair> {
+        self.effects.get(&id).map(|&i| &self.nodes[i]).or_else(|| self.get(id))
+    }
+
+    /// Ability nodes, without the effect nodes that share their prefix.
+    /// Nodes exported without a base class count as abilities
+    pub fn abilities(&self) -> impl Iterator<Item = &NodeObjPair> {
+        self.nodes("abl").filter(|pair| pair.node.base_class != EFFECT_BASE_CLASS)
+    }
+
+    /// All nodes of one kind, e.g. `nodes("abl")` for abilities
+    pub fn nodes<'a>(&'a self, prefix: &'a str) -> impl Iterator<Item = &'a NodeObjPair> + 'a {
+        self.nodes.iter().filter(move |pair| {
+            pair.node.fqn.strip_prefix(prefix).is_some_and(|rest| rest.starts_with('.'))
+        })
+    }
+}
+
+/// `Ok(None)` if the file doesn't exist
+fn read_json<T: serde::de::DeserializeOwned>(path: &Path) -> Result<Option<T>, GameDataError> {
+    let file = path.display().to_string();
+    let reader = match File::open(path) {
+        Ok(f) => BufReader::new(f),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(GameDataError::Io { file, err }),
+    };
+    serde_json::from_reader(reader).map(Some).map_err(|err| GameDataError::Json { file, err })
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::gom::field_id;
+    use std::time::Duration;
+    use crate::parse::{ClassView, CodeClass, Field, FieldValue, Id, Int, List, LookupList, Node};
+    use crate::stb::LocText;
+
+    /// Node `id` called `fqn` with these fields, for building test data. It has no base class,
+    /// set `node.base_class` for abilities and effects
+    pub(crate) fn node(id: u64, fqn: &str, fields: Vec<Field>) -> NodeObjPair {
+        NodeObjPair {
+            node: Node { id: id.to_string(), fqn: fqn.to_string(), base_class: String::new() },
+            obj: CodeClass(fields),
+        }
+    }
+
+    /// Field called `name` in the GOM
+    pub(crate) fn field(name: &str, value: FieldValue) -> Field {
+        Field { id: Id(field_id(name).expect("GOM field")), value }
+    }
+
+    #[test]
+    fn lookup() -> Result<(), GameDataError> {
+        let data = GameData::from_nodes(vec![
+            node(16140902893827567561, "abl.sith_inquisitor.force_lightning", vec![]),
+            node(16141067504602942620, "dis.sith_inquisitor.darkness", vec![]),
+            node(16140973599688231714, "ablx.not_an_ability", vec![]),
+        ])?;
+        assert_eq!(3, data.len());
+        assert_eq!("dis.sith_inquisitor.darkness", data.get(16141067504602942620).unwrap().node.fqn);
+        assert_eq!("16140902893827567561", data.get_by_fqn("abl.sith_inquisitor.force_lightning").unwrap().node.id);
+        assert_eq!("abl.sith_inquisitor.force_lightning", data.resolve(NodeRef(Id(16140902893827567561))).unwrap().node.fqn);
+        assert!(data.get(1).is_none());
+        assert_eq!(Some("dis.sith_inquisitor.darkness"), data.name(16141067504602942620));
+        assert_eq!(1, data.nodes("abl").count());
+
+        let mut bad = node(0, "abl.bad", vec![]);
+        bad.node.id = "abc".to_string();
+        assert!(matches!(GameData::from_nodes(vec![bad]), Err(GameDataError::BadId { .. })));
+        Ok(())
+    }
+
+    #[test]
+    fn from_cache() -> Result<(), GameDataError> {
+        let fields = vec![
+            Field { id: Id(10), value: FieldValue::Boolean(true) },
+            Field { id: Id(11), value: FieldValue::List(List(vec![FieldValue::Int(Int(1)), FieldValue::Int(Int(-2))])) },
+            Field { id: Id(12), value: FieldValue::LookupList(LookupList(vec![(FieldValue::Id(Id(u64::MAX)), FieldValue::Float(0.5))])) },
+            Field { id: Id(13), value: FieldValue::ClassView(ClassView(vec![Field { id: Id(14), value: FieldValue::String("nested".to_string()) }])) },
+            Field { id: Id(15), value: FieldValue::NodeRef(NodeRef(Id(16140902893827567561))) },
+            Field { id: Id(16), value: FieldValue::TimeInterval(Duration::from_millis(1500)) },
+            Field { id: Id(17), value: FieldValue::Other(serde_json::json!({"unknown": [1, "two"]})) },
+        ];
+        let strings = HashMap::from([("str.abl".to_string(), HashMap::from([(1, "Cached".to_string())]))]);
+        let bytes = cache::encode(&CacheData { nodes: vec![node(16140902893827567561, "abl.test.cached", fields.clone())], strings });
+        let data = GameData::from_cache(&bytes)?;
+        assert_eq!(CodeClass(fields), data.get(16140902893827567561).unwrap().obj);
+        assert_eq!(Some("Cached"), data.strings().get(&LocText { bucket: "str.abl".to_string(), id: 1 }));
+        Ok(())
+    }
+
+    #[test]
+    fn from_archives() -> Result<(), GameDataError> {
+        use crate::node::tests::{archive_of, bucket, buckets_info};
+        let archives = archive_of("game_data_from_archives", &[
+            ("/resources/systemgenerated/buckets.info".to_string(), buckets_info(&["0.bkt"])),
+            ("/resources/systemgenerated/buckets/0.bkt".to_string(),
+             bucket(&[(16140902893827567561, "abl.test.bucket", &[0]), (5, "cdx.test.ignored", &[0])])),
+        ]).map_err(NodeError::from)?;
+        let data = GameData::from_archives(&archives)?;
+        assert_eq!(1, data.len());
+        assert_eq!("abl.test.bucket", data.get(16140902893827567561).unwrap().node.fqn);
+        Ok(())
+    }
+}
@@ -0,0 +1,213 @@
+#![allow(dead_code)]
+
+// Gear loadouts. Every slot holds an item plus the mods, enhancement and augment put into it,
+// all of them itm.* nodes whose stats are added up into the wearer's `Stats`.
+// A set bonus is the ability the set grants, its effects are applied as passives.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use serde::Deserialize;
+use crate::action::WeaponDamage;
+use crate::effect::{ability_effects, key_of, Effect};
+use crate::game_data::GameData;
+use crate::gom::{FieldError, Fields};
+use crate::parse::{FieldValue, NodeObjPair};
+use crate::stats::{Stat, Stats};
+
+/// Stat lookup of an item, by field id as in `node_itm` in jedipedia.js
+static ITM_STATS_ID: u64 = 4611686038375170013;
+// Stat ids of weapon damage, see `statLookup` in jedipedia.js
+static STAT_MAX_WEAPON_DAMAGE: u64 = 12;
+static STAT_MIN_WEAPON_DAMAGE: u64 = 13;
+
+#[derive(Debug)]
+pub enum GearError {
+    UnknownItem(ItemRef),
+    Field { item: String, err: FieldError },
+}
+
+impl fmt::Display for GearError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GearError::UnknownItem(item) => write!(f, "no item node {item}"),
+            GearError::Field { item, err } => write!(f, "{item}: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for GearError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, strum::Display)]
+pub enum Slot {
+    MainHand,
+    OffHand,
+    Head,
+    Chest,
+    Hands,
+    Waist,
+    Legs,
+    Feet,
+    Wrists,
+    Ear,
+    Implant1,
+    Implant2,
+    Relic1,
+    Relic2,
+}
+
+/// A node by fqn or id
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(untagged)]
+pub enum ItemRef {
+    Id(u64),
+    Fqn(String),
+}
+
+impl fmt::Display for ItemRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ItemRef::Id(id) => write!(f, "{id}"),
+            ItemRef::Fqn(fqn) => write!(f, "{fqn}"),
+        }
+    }
+}
+
+impl ItemRef {
+    fn resolve<'a>(&self, data: &'a GameData) -> Result<&'a NodeObjPair, GearError> {
+        match self {
+            ItemRef::Id(id) => data.get(*id),
+            ItemRef::Fqn(fqn) => data.get_by_fqn(fqn),
+        }.ok_or_else(|| GearError::UnknownItem(self.clone()))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct GearPiece {
+    pub item: ItemRef,
+    /// Armoring, hilt or barrel, mod and enhancement
+    #[serde(default)]
+    pub mods: Vec<ItemRef>,
+    #[serde(default)]
+    pub augment: Option<ItemRef>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct Loadout {
+    pub slots: BTreeMap<Slot, GearPiece>,
+    /// Ability of the set bonus tier the loadout has, e.g. the 6 piece bonus. Which items belong
+    /// to a set isn't read, so the piece count isn't checked and picking the tier is up to the loadout
+    #[serde(default)]
+    pub set_bonus: Option<ItemRef>,
+}
+
+/// Everything a loadout gives
+#[derive(Debug, Clone, Default)]
+pub struct GearTotals {
+    pub stats: HashMap<Stat, f64>,
+    /// Stats `Stats` doesn't have, by stat id
+    pub other: HashMap<u64, f64>,
+    /// From the main hand
+    pub weapon: Option<WeaponDamage>,
+    pub set_bonus: Vec<Effect>,
+}
+
+/// Stats of one item node, by stat id
+pub fn item_stats(pair: &NodeObjPair) -> Result<Vec<(u64, f64)>, FieldError> {
+    let field = format!("{ITM_STATS_ID}");
+    let lookup = match pair.obj.get_by_id(ITM_STATS_ID) {
+        None => return Ok(vec![]),
+        Some(FieldValue::LookupList(lookup)) => lookup,
+        Some(other) => return Err(FieldError::WrongType { field, expected: "LookupList", found: other.type_name() }),
+    };
+    lookup.0.iter().enumerate().map(|(i, (key, value))| {
+        let stat = key_of(key).ok_or_else(|| FieldError::invalid(&field, format!("[{i}] has key {key:?}")))?;
+        let value = value.as_number().ok_or_else(|| FieldError::invalid(&field, format!("[{i}] has value {value:?}")))?;
+        Ok((stat, value))
+    }).collect()
+}
+
+impl Loadout {
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+
+    pub fn totals(&self, data: &GameData) -> Result<GearTotals, GearError> {
+        let mut totals = GearTotals::default();
+        for (slot, piece) in &self.slots {
+            let mut weapon = (0.0, 0.0);
+            for item in std::iter::once(&piece.item).chain(&piece.mods).chain(&piece.augment) {
+                let pair = item.resolve(data)?;
+                let stats = item_stats(pair).map_err(|err| GearError::Field { item: pair.node.fqn.clone(), err })?;
+                for (id, value) in stats {
+                    match Stat::from_id(id) {
+                        Some(stat) => *totals.stats.entry(stat).or_default() += value,
+                        None if id == STAT_MIN_WEAPON_DAMAGE => weapon.0 += value,
+                        None if id == STAT_MAX_WEAPON_DAMAGE => weapon.1 += value,
+                        None => *totals.other.entry(id).or_default() += value,
+                    }
+                }
+            }
+            if *slot == Slot::MainHand {
+                totals.weapon = Some(WeaponDamage { min: weapon.0, max: weapon.1 });
+            }
+        }
+        if let Some(set_bonus) = &self.set_bonus {
+            let pair = set_bonus.resolve(data)?;
+            let (effects, _) = ability_effects(&pair.obj, data).map_err(|err| GearError::Field { item: pair.node.fqn.clone(), err })?;
+            totals.set_bonus = effects;
+        }
+        Ok(totals)
+    }
+}
+
+impl GearTotals {
+    /// Adds the gear stats to the bonus layer
+    pub fn apply(&self, stats: &mut Stats) {
+        for (&stat, &value) in &self.stats {
+            stats.value_mut(stat).bonus += value;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_data::tests::node;
+    use crate::parse::{Field, Id, LookupList};
+
+    fn item(id: u64, fqn: &str, stats: &[(u64, f32)]) -> NodeObjPair {
+        let stats = stats.iter().map(|&(stat, value)| (FieldValue::Enum(Id(stat)), FieldValue::Float(value))).collect();
+        node(id, fqn, vec![Field { id: Id(ITM_STATS_ID), value: FieldValue::LookupList(LookupList(stats)) }])
+    }
+
+    #[test]
+    fn totals() -> Result<(), Box<dyn std::error::Error>> {
+        let data = GameData::from_nodes(vec![
+            item(1, "itm.gen.lightsaber", &[(12, 3661.0), (13, 2441.0), (158, 2000.0)]),
+            item(2, "itm.mod.mastery", &[(414, 300.0), (155, 100.0)]),
+            item(3, "itm.augment.crit", &[(156, 120.0), (5, 80.0)]),
+            item(4, "itm.gen.chest", &[(414, 500.0), (43, 1.0)]),
+        ])?;
+        let loadout = Loadout::from_json(r#"{
+            "slots": {
+                "MainHand": {"item": "itm.gen.lightsaber", "mods": [2], "augment": "itm.augment.crit"},
+                "Chest": {"item": 4}
+            }
+        }"#)?;
+        let totals = loadout.totals(&data)?;
+        assert_eq!(Some(&800.0), totals.stats.get(&Stat::Mastery));
+        assert_eq!(Some(&120.0), totals.stats.get(&Stat::Critical));
+        assert_eq!(Some(&2000.0), totals.stats.get(&Stat::ForcePower));
+        assert_eq!(Some(&1.0), totals.other.get(&43));
+        let weapon = totals.weapon.as_ref().unwrap();
+        assert_eq!((2441.0, 3661.0), (weapon.min, weapon.max));
+
+        let mut stats = Stats::default();
+        totals.apply(&mut stats);
+        assert_eq!(800.0, stats.get(Stat::Mastery));
+
+        let missing = Loadout::from_json(r#"{"slots": {"Head": {"item": "itm.gen.missing"}}}"#)?;
+        assert!(matches!(missing.totals(&data), Err(GearError::UnknownItem(ItemRef::Fqn(_)))));
+        Ok(())
+    }
+}
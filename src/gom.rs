@@ -0,0 +1,228 @@
+#![allow(dead_code)]
+
+// Name based access to node fields, using the field table from GOM.json.
+// Field ids stay stable between patches while their position in a node does not.
+
+use std::fmt;
+use std::time::Duration;
+use crate::parse::{ClassView, CodeClass, Field, FieldValue, Id, Int, LookupList, NodeRef};
+
+include!(concat!(env!("OUT_DIR"), "/fields.rs"));
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FieldError {
+    /// Name isn't in the GOM field table
+    UnknownName(String),
+    /// Name is known, but the node doesn't have the field
+    Missing(String),
+    WrongType { field: String, expected: &'static str, found: &'static str },
+    Invalid { field: String, msg: String },
+}
+
+impl FieldError {
+    pub fn invalid(field: &str, msg: impl Into<String>) -> Self {
+        FieldError::Invalid { field: field.to_string(), msg: msg.into() }
+    }
+
+    /// Prefixes the field with where it was found, e.g. `effSubEffects[0].effActions`
+    pub fn within(self, outer: &str) -> Self {
+        match self {
+            FieldError::UnknownName(_) => self,
+            FieldError::Missing(name) => FieldError::Missing(format!("{outer}.{name}")),
+            FieldError::WrongType { field, expected, found } => FieldError::WrongType { field: format!("{outer}.{field}"), expected, found },
+            FieldError::Invalid { field, msg } => FieldError::Invalid { field: format!("{outer}.{field}"), msg },
+        }
+    }
+}
+
+/// Nodes leave out fields that have their default value
+pub fn default_if_missing<T: Default>(result: Result<T, FieldError>) -> Result<T, FieldError> {
+    match result {
+        Err(FieldError::Missing(_)) => Ok(T::default()),
+        result => result,
+    }
+}
+
+impl fmt::Display for FieldError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FieldError::UnknownName(name) => write!(f, "unknown field name {name}"),
+            FieldError::Missing(name) => write!(f, "missing field {name}"),
+            FieldError::WrongType { field, expected, found } => write!(f, "field {field} is {found}, expected {expected}"),
+            FieldError::Invalid { field, msg } => write!(f, "field {field}: {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for FieldError {}
+
+impl Field {
+    pub fn name(&self) -> Option<&'static str> {
+        field_name(self.id.0)
+    }
+}
+
+macro_rules! getter {
+    ($fn_name:ident, $variant:ident, $ty:ty, |$v:ident| $conv:expr) => {
+        fn $fn_name(&self, name: &str) -> Result<$ty, FieldError> {
+            match self.get(name)? {
+                FieldValue::$variant($v) => Ok($conv),
+                other => Err(FieldError::WrongType {
+                    field: name.to_string(),
+                    expected: stringify!($variant),
+                    found: other.type_name(),
+                }),
+            }
+        }
+    };
+}
+
+/// Lookup by field name for anything made of fields, i.e. nodes and embedded classes
+pub trait Fields {
+    fn fields(&self) -> &[Field];
+
+    fn get_by_id(&self, id: u64) -> Option<&FieldValue> {
+        self.fields().iter().find(|field| field.id == Id(id)).map(|field| &field.value)
+    }
+
+    /// `Ok(None)` if the node doesn't have the field, an error if the name isn't a GOM field
+    fn get_opt(&self, name: &str) -> Result<Option<&FieldValue>, FieldError> {
+        let id = field_id(name).ok_or_else(|| FieldError::UnknownName(name.to_string()))?;
+        Ok(self.get_by_id(id))
+    }
+
+    fn get(&self, name: &str) -> Result<&FieldValue, FieldError> {
+        self.get_opt(name)?.ok_or_else(|| FieldError::Missing(name.to_string()))
+    }
+
+    getter!(get_id, Id, u64, |v| v.0);
+    getter!(get_int, Int, i64, |v| v.0);
+    getter!(get_bool, Boolean, bool, |v| *v);
+    getter!(get_float, Float, f32, |v| *v);
+    getter!(get_enum, Enum, u64, |v| v.0);
+    getter!(get_string, String, &str, |v| v.as_str());
+    getter!(get_list, List, &[FieldValue], |v| &v.0);
+    getter!(get_lookup, LookupList, &[(FieldValue, FieldValue)], |v| &v.0);
+    getter!(get_class, ClassView, &ClassView, |v| v);
+    getter!(get_node_ref, NodeRef, NodeRef, |v| *v);
+    getter!(get_time, TimeInterval, Duration, |v| *v);
+}
+
+impl Fields for CodeClass {
+    fn fields(&self) -> &[Field] {
+        &self.0
+    }
+}
+
+impl Fields for ClassView {
+    fn fields(&self) -> &[Field] {
+        &self.0
+    }
+}
+
+impl LookupList {
+    pub fn get(&self, key: &FieldValue) -> Option<&FieldValue> {
+        self.0.iter().find(|(k, _)| k == key).map(|(_, v)| v)
+    }
+}
+
+impl FieldValue {
+    pub fn as_id(&self) -> Option<u64> {
+        match self {
+            FieldValue::Id(Id(id)) => Some(*id),
+            _ => None,
+        }
+    }
+
+    pub fn as_int(&self) -> Option<i64> {
+        match self {
+            FieldValue::Int(Int(int)) => Some(*int),
+            _ => None,
+        }
+    }
+
+    /// Ints and floats, e.g. stat values
+    pub fn as_number(&self) -> Option<f64> {
+        match self {
+            FieldValue::Int(Int(int)) => Some(*int as f64),
+            FieldValue::Float(float) => Some(f64::from(*float)),
+            _ => None,
+        }
+    }
+
+    /// Converts an enum value into one of the generated `types` enums
+    pub fn as_enum<T: TryFrom<u64>>(&self) -> Option<T> {
+        match self {
+            FieldValue::Enum(Id(value)) => T::try_from(*value).ok(),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::List;
+
+    #[test]
+    fn unknown_field_name() {
+        let class = CodeClass(vec![Field { id: Id(1), value: FieldValue::Boolean(true) }]);
+        assert_eq!(Some(&FieldValue::Boolean(true)), class.get_by_id(1));
+        assert_eq!(None, class.get_by_id(2));
+        assert_eq!(Err(FieldError::UnknownName("not a gom field".to_string())), class.get("not a gom field"));
+    }
+
+    fn field(name: &str, value: FieldValue) -> Field {
+        Field { id: Id(field_id(name).expect("GOM field")), value }
+    }
+
+    #[test]
+    fn typed_getters() {
+        let view = ClassView(vec![field("effStackLimit", FieldValue::Int(Int(2)))]);
+        let class = CodeClass(vec![
+            field("effId", FieldValue::Id(Id(7))),
+            field("effStackLimit", FieldValue::Int(Int(-3))),
+            field("ablIsPassive", FieldValue::Boolean(true)),
+            field("ablMaxRange", FieldValue::Float(0.4)),
+            field("ablCombatMode", FieldValue::Enum(Id(2))),
+            field("effActionName", FieldValue::String("Damage".to_string())),
+            field("ablEffectIDs", FieldValue::List(List(vec![FieldValue::Id(Id(7))]))),
+            field("effParams", FieldValue::LookupList(LookupList(vec![(FieldValue::Int(Int(1)), FieldValue::Float(2.0))]))),
+            field("effTargetOverrides", FieldValue::ClassView(view.clone())),
+            field("ablEffectZero", FieldValue::NodeRef(NodeRef(Id(8)))),
+            field("ablCooldownTime", FieldValue::Float(1.5)),
+            field("effDuration", FieldValue::TimeInterval(Duration::from_millis(1500))),
+        ]);
+        assert_eq!(Ok(7), class.get_id("effId"));
+        assert_eq!(Ok(-3), class.get_int("effStackLimit"));
+        assert_eq!(Ok(true), class.get_bool("ablIsPassive"));
+        assert_eq!(Ok(0.4), class.get_float("ablMaxRange"));
+        assert_eq!(Ok(2), class.get_enum("ablCombatMode"));
+        assert_eq!(Ok("Damage"), class.get_string("effActionName"));
+        assert_eq!(Ok(&[FieldValue::Id(Id(7))][..]), class.get_list("ablEffectIDs"));
+        assert_eq!(Ok(&[(FieldValue::Int(Int(1)), FieldValue::Float(2.0))][..]), class.get_lookup("effParams"));
+        assert_eq!(Ok(&view), class.get_class("effTargetOverrides"));
+        assert_eq!(Ok(2), class.get_class("effTargetOverrides").and_then(|view| view.get_int("effStackLimit")));
+        assert_eq!(Ok(NodeRef(Id(8))), class.get_node_ref("ablEffectZero"));
+        assert_eq!(Ok(1.5), class.get_float("ablCooldownTime"));
+        assert_eq!(Ok(Duration::from_millis(1500)), class.get_time("effDuration"));
+        assert_eq!(Some("effId"), class.0[0].name());
+
+        assert_eq!(Err(FieldError::WrongType { field: "ablIsPassive".to_string(), expected: "Int", found: FieldValue::Boolean(true).type_name() }),
+                   class.get_int("ablIsPassive"));
+        assert_eq!(Err(FieldError::Missing("ablCastingTime".to_string())), class.get_float("ablCastingTime"));
+        assert_eq!(Ok(0.0), default_if_missing(class.get_float("ablCastingTime")));
+        assert_eq!(Ok(None), class.get_opt("ablCastingTime"));
+    }
+
+    #[test]
+    fn enum_value() {
+        use crate::types::ablAbilityType;
+
+        let variant = ablAbilityType::ablAbilityTypeDiscipline;
+        let value = FieldValue::Enum(Id(u64::from(variant)));
+        assert_eq!(Some(variant), value.as_enum());
+        assert_eq!(None, FieldValue::Enum(Id(0)).as_enum::<ablAbilityType>());
+        assert_eq!(None, FieldValue::Int(Int(1)).as_enum::<ablAbilityType>());
+    }
+}
@@ -1,82 +1,79 @@
 mod utils;
 mod ecs_system;
 mod parse;
-#[allow(non_camel_case_types)]
+mod archive;
+mod node;
+mod gom;
+#[allow(dead_code)]
+mod cache;
+mod game_data;
+mod effect;
+mod action;
+mod trigger;
+mod timeline;
+mod simulation;
+mod batch;
+mod rng;
+mod stats;
+mod gear;
+mod diminishing_returns;
+mod stb;
+mod tooltip;
+mod talent;
+mod discipline;
+mod rebuild;
+#[allow(non_camel_case_types, clippy::enum_variant_names)]
 mod types;
 
 
-use std::str::FromStr;
-
-use ecs_system::{get_abilities, TreeChoices, TreeChoice, CombatStyle};
+use ecs_system::{get_abilities, TreeChoices};
 use utils::constants::*;
+use utils::formulas::{armor_reduction, tooltip_damage};
 use bevy::prelude::*;
+use action::WeaponDamage;
+use game_data::GameData;
+use simulation::Simulation;
+use stats::{Stat, Stats};
+use diminishing_returns::DiminishingReturns;
+use talent::Talents;
 
 #[allow(dead_code)]
-struct Gear {
-    weapon_min: f64,
-    weapon_max: f64,
-    gear_crit: f64,
-    gear_mastery: f64,
-    gear_power: f64,
+/// Thrash tooltip, with the talents' damage modifiers for `ability`
+fn tooltip_dmg(stats: &Stats, weapon: &WeaponDamage, talents: &Talents, ability: u64) -> (f64, f64) {
+    let dmg_min = tooltip_damage(weapon.min, AMP, COEFFICIENT, stats.bonus_damage(), SHP);
+    let dmg_max = tooltip_damage(weapon.max, AMP, COEFFICIENT, stats.bonus_damage(), SHP);
+    let tooltip_min = (dmg_min + talents.flat_damage(ability)) * talents.damage_multiplier(ability);
+    let tooltip_max = (dmg_max + talents.flat_damage(ability)) * talents.damage_multiplier(ability);
+    (tooltip_min, tooltip_max)
 }
 
 #[allow(dead_code)]
-impl Gear {
-    fn get_mastery(&self) -> f64 {
-        let mastery = BASE_MASTERY + DATACRON_MASTERY + self.gear_mastery;
-        mastery * 1.0 + CLASS_BUFF_MASTERY
-    }
-
-    fn get_power(&self) -> f64 {
-        self.gear_power
-    }
-
-    fn get_bonus_dmg(&self) -> f64 {
-        let bonus_dmg = self.get_mastery() * MASTERY_DMG_BONUS + self.get_power() * POWER_DMG_BONUS;
-        bonus_dmg * 1.0 + CLASS_BUFF_BONUS_DMG
-    }
-
-    fn get_crit_surge(&self) -> f64 {
-        BASE_CRIT + COMPANION_BUFF_CRIT_SURGE + 0.3 * (1.0 - (1.0f64 - 0.01/0.3).powf(self.gear_crit/LEVEL/CRIT_MAGIC))
-    }
-
-    fn tooltip_dmg(&self) -> (f64, f64) {
-        let dmg_min = self.weapon_min * (1.0 + AMP) + COEFFICIENT * self.get_bonus_dmg() + SHP * BASE_LEVEL_DMG;
-        let dmg_max = self.weapon_max * (1.0 + AMP) + COEFFICIENT * self.get_bonus_dmg() + SHP * BASE_LEVEL_DMG;
-        let tooltip_min = dmg_min * (1.0 + PASSIVE_MODS);
-        let tooltip_max = dmg_max * (1.0 + PASSIVE_MODS);
-        (tooltip_min, tooltip_max)
-    }
-
-    fn dummy_dmg(&self) -> (f64, f64) {
-        let (tooltip_min, tooltip_max) = self.tooltip_dmg();
-        let armor = BOSS_ARMOR * (1.0 - ARMOR_DEBUFF);
-        let armor_dmg_reduction = armor / (armor + ARMOR_MAGIC_1 * LEVEL + ARMOR_MAGIC_2);
-        let dummy_min = tooltip_min * (1.0 - armor_dmg_reduction) / (1.0 - MELEE_DMG_BUFF);
-        let dummy_max = tooltip_max * (1.0 - armor_dmg_reduction) / (1.0 - MELEE_DMG_BUFF);
-        (dummy_min, dummy_max)
-    }
+fn dummy_dmg(stats: &Stats, weapon: &WeaponDamage, talents: &Talents, ability: u64) -> (f64, f64) {
+    let (tooltip_min, tooltip_max) = tooltip_dmg(stats, weapon, talents, ability);
+    let armor_dmg_reduction = armor_reduction(BOSS_ARMOR * (1.0 - ARMOR_DEBUFF));
+    let dummy_min = tooltip_min * (1.0 - armor_dmg_reduction) / (1.0 - MELEE_DMG_BUFF);
+    let dummy_max = tooltip_max * (1.0 - armor_dmg_reduction) / (1.0 - MELEE_DMG_BUFF);
+    (dummy_min, dummy_max)
+}
 
-    fn dummy_crit(&self) -> (f64, f64) {
-        let (dummy_min, dummy_max) = self.dummy_dmg();
-        let crit_min = dummy_min * (1.0 + self.get_crit_surge());
-        let crit_max = dummy_max * (1.0 + self.get_crit_surge());
-        (crit_min, crit_max)
-    }
+#[allow(dead_code)]
+fn dummy_crit(stats: &Stats, weapon: &WeaponDamage, talents: &Talents, ability: u64, dr: &DiminishingReturns) -> (f64, f64) {
+    let (dummy_min, dummy_max) = dummy_dmg(stats, weapon, talents, ability);
+    (dummy_min * stats.crit_multiplier(dr), dummy_max * stats.crit_multiplier(dr))
 }
 
 #[allow(dead_code)]
-fn simple_dmg_calc(gear: Gear) {
-    let (tooltip_min, tooltip_max) = gear.tooltip_dmg();
+fn simple_dmg_calc(stats: &Stats, weapon: &WeaponDamage, talents: &Talents, ability: u64, dr: &DiminishingReturns) {
+    let (tooltip_min, tooltip_max) = tooltip_dmg(stats, weapon, talents, ability);
     println!("Tooltip min: {}", tooltip_min);
     println!("Tooltip max: {}", tooltip_max);
     println!("Tooltip: {} - {}", tooltip_min.round(), tooltip_max.round());
 
-    let (dummy_min, dummy_max) = gear.dummy_dmg();
+    let (dummy_min, dummy_max) = dummy_dmg(stats, weapon, talents, ability);
     println!("Dummy min: {}", dummy_min);
     println!("Dummy max: {}", dummy_max);
 
-    let (crit_min, crit_max) = gear.dummy_crit();
+    let (crit_min, crit_max) = dummy_crit(stats, weapon, talents, ability, dr);
     println!("Crit min: {}", crit_min);
     println!("Crit max: {}", crit_max);
 }
@@ -94,7 +91,7 @@ fn test_scene_system(mut commands: Commands, q: Query<Entity, (With<ecs_system::
     for entity in &q {
         info!("Entity: {}", entity.index());
         commands.add(move |world: &mut World| {
-            let scene = DynamicSceneBuilder::from_world(&world).extract_entity(entity).build();
+            let scene = DynamicSceneBuilder::from_world(world).extract_entity(entity).build();
             let type_registry = world.resource::<AppTypeRegistry>();
             let serialized_abilities = scene.serialize_ron(type_registry).unwrap();
             info!("{}", serialized_abilities);
@@ -103,46 +100,63 @@ fn test_scene_system(mut commands: Commands, q: Query<Entity, (With<ecs_system::
 }
 
 fn main() {
-    let _dumb_saber = Gear {
-        weapon_min: 46.0,
-        weapon_max: 69.0,
-        gear_crit: 5.0,
-        gear_mastery: 10.0,
-        gear_power: 6.0,
-    };
-    let _real_saber = Gear {
-        weapon_min: 2441.0,
-        weapon_max: 3661.0,
-        gear_crit: 655.0,
-        gear_mastery: 1223.0,
-        gear_power: 940.0,
-    };
-
-    // simple_dmg_calc(dumb_saber);
-
-    let mut app = App::new();
+    // `rebuild <Assets dir> [data dir]` refreshes data/*.json from a game install
+    let args: Vec<String> = std::env::args().collect();
+    if args.get(1).is_some_and(|arg| arg == "rebuild") {
+        let Some(assets) = args.get(2) else {
+            eprintln!("usage: {} rebuild <Assets dir> [data dir]", args[0]);
+            std::process::exit(2);
+        };
+        let out = args.get(3).map_or(ecs_system::DATA_PATH, String::as_str);
+        match rebuild::rebuild_dir(assets, out) {
+            Ok(count) => println!("wrote {count} nodes to {out}, run cargo build to update the cache"),
+            Err(err) => {
+                eprintln!("rebuild failed: {err}");
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    let _dumb_saber = (
+        Stats::player().with(Stat::Critical, 5.0).with(Stat::Mastery, 10.0).with(Stat::Power, 6.0),
+        WeaponDamage { min: 46.0, max: 69.0 },
+    );
+    let _real_saber = (
+        Stats::player().with(Stat::Critical, 655.0).with(Stat::Mastery, 1223.0).with(Stat::Power, 940.0),
+        WeaponDamage { min: 2441.0, max: 3661.0 },
+    );
+
+    let dr = DiminishingReturns::load(ecs_system::DATA_PATH).unwrap_or_else(|err| {
+        eprintln!("couldn't load diminishing returns, using the built-in ones: {err}");
+        DiminishingReturns::builtin()
+    });
+    // simple_dmg_calc(&dumb_saber.0, &dumb_saber.1, &Talents::default(), 0, &dr);
+
+    let data = GameData::builtin().unwrap_or_else(|err| panic!("couldn't load game data: {err}"));
+
+    let mut sim = Simulation::new(data).diminishing_returns(dr);
+    let app = sim.app_mut();
     include!(concat!(env!("OUT_DIR"), "/type_registration.rs"));
     app
-        .add_plugins(MinimalPlugins.build().add(bevy::log::LogPlugin::default()))
+        .add_plugins(bevy::log::LogPlugin::default())
         // .add_systems(Startup, (add_shit_system, test_scene_system))
         .add_systems(Startup, add_shit_system)
-        .add_systems(Update, test_scene_system)
-        .run();
-
-    // let choices = TreeChoices {
-    //     combat_style: CombatStyle::from_str("darkness").unwrap(),
-    //     choices: [
-    //         TreeChoice::Right,
-    //         TreeChoice::Left,
-    //         TreeChoice::Right,
-    //         TreeChoice::Right,
-    //         TreeChoice::Middle,
-    //         TreeChoice::Left,
-    //         TreeChoice::Left,
-    //         TreeChoice::Middle,
-    //     ]
-    // };
-    // get_abilities(choices);
-    // ecs_system::read_dis();
+        .add_systems(Update, test_scene_system);
+
+    // A build like darkness:RLRRMLLM can be passed as the first argument
+    if let Some(code) = args.get(1) {
+        let data = sim.app_mut().world.resource::<GameData>();
+        let abilities = TreeChoices::from_code(code, data).and_then(|choices| Ok(get_abilities(choices, data)?));
+        if let Err(err) = abilities {
+            eprintln!("bad build {code}: {err}");
+            std::process::exit(2);
+        }
+    }
+
+    let result = sim.run();
+    info!("{result:?}");
+
+    // ecs_system::read_dis(&ecs_system::CombatStyle::Darkness, sim.app_mut().world.resource::<GameData>());
     // parse::test();
 }
@@ -0,0 +1,659 @@
+#![allow(dead_code)]
+
+// Binary GOM node decoding, following `readNODE`, `readBKT`, `nodeGetObj`,
+// `file_node_readfield` and `readVarInt` in jedipedia.js. The enum and field tables of
+// client.gom are read like `readGOM` does.
+//
+// Values are decoded into the same JSON shape Jedipedia exports (BigInt objects,
+// `{type, list}` lists, `{id, type, value}` fields) and then run through the normal
+// deserializer, so nodes read from the client are identical to the ones from data/*.json.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::Read;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use crate::archive::{ArchiveError, Archives};
+use crate::parse::{CodeClass, Field, Node, NodeObjPair};
+
+static PROT_MAGIC: u32 = 0x544F5250; // "PROT"
+static PBUK_MAGIC: u32 = 0x4B554250; // "PBUK"
+static PBCK_MAGIC: u32 = 0x4B434250; // "PBCK"
+static DBLB_MAGIC: u32 = 0x424C4244; // "DBLB"
+static BUCKETS_PATH: &str = "/resources/systemgenerated/buckets";
+/// Names of all bucket files, as read by `readInfoPbck`
+static BUCKETS_INFO_PATH: &str = "/resources/systemgenerated/buckets.info";
+static PROTOTYPES_PATH: &str = "/resources/systemgenerated/prototypes";
+static CLIENT_GOM_PATH: &str = "/resources/systemgenerated/client.gom";
+
+#[derive(Debug)]
+pub enum NodeError {
+    Archive(ArchiveError),
+    Eof(usize),
+    Format { pos: usize, msg: String },
+}
+
+impl fmt::Display for NodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NodeError::Archive(e) => write!(f, "{e}"),
+            NodeError::Eof(pos) => write!(f, "unexpected end of node data at {pos}"),
+            NodeError::Format { pos, msg } => write!(f, "bad node data at {pos}: {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for NodeError {}
+
+impl From<ArchiveError> for NodeError {
+    fn from(value: ArchiveError) -> Self {
+        NodeError::Archive(value)
+    }
+}
+
+/// Variable length integer, as read by `readVarInt`.
+/// Bytes below 0xC0 are the value itself, 0xC0-0xC7 are followed by 1-8 big endian
+/// bytes of a negative value and 0xC8-0xCF by those of a positive one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VarInt {
+    pub negative: bool,
+    pub magnitude: u64,
+    pub len: usize,
+}
+
+impl VarInt {
+    /// Two's complement value, used for id offsets which wrap around
+    pub fn wrapping_u64(&self) -> u64 {
+        if self.negative { self.magnitude.wrapping_neg() } else { self.magnitude }
+    }
+
+    /// Same layout as Jedipedia's BigInt objects
+    fn to_json(self) -> Value {
+        json!({
+            "sign": if self.negative { -1 } else { 1 },
+            "intLo": self.magnitude as u32,
+            "intHi": (self.magnitude >> 32) as u32,
+            "len": self.len,
+        })
+    }
+}
+
+pub struct NodeReader<'a> {
+    buf: &'a [u8],
+    pub pos: usize,
+}
+
+impl<'a> NodeReader<'a> {
+    pub fn new(buf: &'a [u8]) -> Self {
+        Self { buf, pos: 0 }
+    }
+
+    fn error<T>(&self, msg: impl Into<String>) -> Result<T, NodeError> {
+        Err(NodeError::Format { pos: self.pos, msg: msg.into() })
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], NodeError> {
+        let bytes = self.buf.get(self.pos..self.pos + len).ok_or(NodeError::Eof(self.pos))?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn skip(&mut self, len: usize) -> Result<(), NodeError> {
+        self.bytes(len).map(|_| ())
+    }
+
+    pub fn peek_u8(&self) -> Result<u8, NodeError> {
+        self.buf.get(self.pos).copied().ok_or(NodeError::Eof(self.pos))
+    }
+
+    pub fn u8(&mut self) -> Result<u8, NodeError> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    pub fn u16(&mut self) -> Result<u16, NodeError> {
+        Ok(u16::from_le_bytes(self.bytes(2)?.try_into().expect("slice is 2 bytes")))
+    }
+
+    pub fn u32(&mut self) -> Result<u32, NodeError> {
+        Ok(u32::from_le_bytes(self.bytes(4)?.try_into().expect("slice is 4 bytes")))
+    }
+
+    pub fn u64(&mut self) -> Result<u64, NodeError> {
+        Ok(u64::from_le_bytes(self.bytes(8)?.try_into().expect("slice is 8 bytes")))
+    }
+
+    pub fn f32(&mut self) -> Result<f32, NodeError> {
+        Ok(f32::from_le_bytes(self.bytes(4)?.try_into().expect("slice is 4 bytes")))
+    }
+
+    pub fn var_int(&mut self) -> Result<VarInt, NodeError> {
+        let first = self.u8()?;
+        let (negative, len) = match first {
+            0xC0..=0xC7 => (true, (first - 0xC0 + 1) as usize),
+            0xC8..=0xCF => (false, (first - 0xC8 + 1) as usize),
+            _ => return Ok(VarInt { negative: false, magnitude: first as u64, len: 1 }),
+        };
+        let magnitude = self.bytes(len)?.iter().fold(0u64, |acc, &b| (acc << 8) | b as u64);
+        Ok(VarInt { negative, magnitude, len: len + 1 })
+    }
+
+    /// Lengths and counts, which are always positive 32-bit integers
+    fn count(&mut self) -> Result<usize, NodeError> {
+        let var = self.var_int()?;
+        if var.negative || var.magnitude > u32::MAX as u64 {
+            return self.error("expected positive 32-bit integer as length");
+        }
+        Ok(var.magnitude as usize)
+    }
+
+    fn version(&mut self) -> Result<(u16, u16), NodeError> {
+        Ok((self.u16()?, self.u16()?))
+    }
+
+    /// Null terminated string starting at `pos`
+    fn c_string_at(&self, pos: usize) -> Result<String, NodeError> {
+        let rest = self.buf.get(pos..).ok_or(NodeError::Eof(pos))?;
+        let end = rest.iter().position(|&b| b == 0).ok_or(NodeError::Eof(self.buf.len()))?;
+        Ok(String::from_utf8_lossy(&rest[..end]).into_owned())
+    }
+
+    /// One value of the given DOM type, in Jedipedia's JSON layout
+    pub fn read_value(&mut self, value_type: u8) -> Result<Value, NodeError> {
+        Ok(match value_type {
+            // Id, Int, Enum, ScriptRef, NodeRef, TimeInterval, Date
+            1 | 2 | 5 | 14 | 15 | 20 | 21 => self.var_int()?.to_json(),
+            3 => match self.u8()? {
+                0 => Value::Bool(false),
+                1 => Value::Bool(true),
+                b => return self.error(format!("unexpected bool {b}")),
+            },
+            4 => json!(self.f32()?),
+            6 => {
+                let len = self.count()?;
+                Value::String(String::from_utf8_lossy(self.bytes(len)?).into_owned())
+            },
+            7 => {
+                let list_type = self.u8()?;
+                let count = self.list_count()?;
+                let mut list = Vec::with_capacity(count);
+                for i in 0..count {
+                    let index = self.count()?;
+                    if index != i + 1 {
+                        return self.error(format!("expected list index {} but got {index}", i + 1));
+                    }
+                    list.push(self.read_value(list_type)?);
+                }
+                json!({ "type": list_type, "list": list })
+            },
+            8 => {
+                let index_type = self.u8()?;
+                let list_type = self.u8()?;
+                let count = self.list_count()?;
+                let mut list = Vec::with_capacity(count);
+                for _ in 0..count {
+                    if self.peek_u8()? == 0xD2 { self.skip(1)? }
+                    let key = self.read_value(index_type)?;
+                    let val = self.read_value(list_type)?;
+                    list.push(json!({ "key": key, "val": val }));
+                }
+                json!({ "indexType": index_type, "type": list_type, "list": list })
+            },
+            9 => {
+                self.skip(1)?;
+                let count = self.count()?;
+                Value::Array(self.read_fields_json(count, "val")?)
+            },
+            18 => json!([self.f32()?, self.f32()?, self.f32()?]),
+            _ => return self.error(format!("unknown DOM type {value_type}")),
+        })
+    }
+
+    fn list_count(&mut self) -> Result<usize, NodeError> {
+        let count = self.count()?;
+        let count2 = self.count()?;
+        if count != count2 {
+            return self.error(format!("list counts differ ({count} != {count2})"));
+        }
+        Ok(count)
+    }
+
+    // Top level fields store their value as "value", fields of a ClassView as "val"
+    fn read_fields_json(&mut self, count: usize, value_key: &str) -> Result<Vec<Value>, NodeError> {
+        let mut id = 0u64;
+        let mut fields = Vec::with_capacity(count);
+        for _ in 0..count {
+            id = id.wrapping_add(self.var_int()?.wrapping_u64());
+            let field_type = self.u8()?;
+            let value = self.read_value(field_type)?;
+            fields.push(json!({ "id": id.to_string(), "type": field_type, value_key: value }));
+        }
+        Ok(fields)
+    }
+
+    /// Field list of a node object, ids are stored as offsets from the previous field
+    pub fn read_fields(&mut self, count: usize) -> Result<CodeClass, NodeError> {
+        let pos = self.pos;
+        self.read_fields_json(count, "value")?
+            .into_iter()
+            .map(|json| Field::deserialize(json)
+                .map_err(|e| NodeError::Format { pos, msg: e.to_string() }))
+            .collect::<Result<Vec<_>, _>>()
+            .map(CodeClass)
+    }
+}
+
+/// Decodes a prototype file, `/resources/systemgenerated/prototypes/<id>.node`
+pub fn read_prototype(data: &[u8]) -> Result<NodeObjPair, NodeError> {
+    let mut reader = NodeReader::new(data);
+    if reader.u32()? != PROT_MAGIC { return reader.error("expected magic PROT") }
+    let (major, minor) = reader.version()?;
+    if major != 2 || (minor != 4 && minor != 5) {
+        return reader.error(format!("expected version 2.4 or 2.5 but got {major}.{minor}"));
+    }
+    let id = reader.u64()?;
+    let fqn_len = reader.u32()? as usize;
+    let fqn = String::from_utf8_lossy(reader.bytes(fqn_len)?).trim_end_matches('\0').to_string();
+    let desc_len = reader.u32()? as usize;
+    reader.skip(desc_len)?;
+    reader.skip(4 + 4)?; // Constants 3 and 1
+    let base_class = reader.u64()?;
+    if minor == 4 { reader.skip(4)? }
+    let num_glommed = reader.u32()? as usize;
+    reader.skip(num_glommed * 8)?;
+    reader.skip(1)?; // Constant 1
+    if reader.u16()? >= 3 {
+        reader.skip(1)?; // Stream style
+    }
+    reader.skip(4 + 1)?; // Node length and an unknown byte
+
+    let count = reader.count()?;
+    let obj = reader.read_fields(count)?;
+    Ok(NodeObjPair {
+        node: Node {
+            id: id.to_string(),
+            fqn,
+            base_class: base_class.to_string(),
+        },
+        obj,
+    })
+}
+
+/// Decodes every node in a bucket file, `/resources/systemgenerated/buckets/<n>.bkt`
+pub fn read_bucket(data: &[u8], file_name: &str) -> Result<Vec<NodeObjPair>, NodeError> {
+    read_bucket_json(data, file_name)?.into_iter().map(to_pair).collect()
+}
+
+/// Same as [`read_bucket`], but leaves the nodes in Jedipedia's JSON shape
+pub fn read_bucket_json(data: &[u8], file_name: &str) -> Result<Vec<Value>, NodeError> {
+    let mut reader = NodeReader::new(data);
+    if reader.u32()? != PBUK_MAGIC { return reader.error("expected magic PBUK") }
+    let (major, minor) = reader.version()?;
+    if major != 2 || (minor != 4 && minor != 5) {
+        return reader.error(format!("expected version 2.4 or 2.5 but got {major}.{minor}"));
+    }
+
+    let mut nodes = vec![];
+    let end = data.len().saturating_sub(12);
+    while reader.pos < end {
+        let dblb_len = reader.u32()? as usize;
+        let dblb_start = reader.pos;
+        if reader.u32()? != DBLB_MAGIC { return reader.error("expected magic DBLB") }
+        let dblb_version = reader.u32()?;
+        if dblb_version != 1 && dblb_version != 2 {
+            return reader.error(format!("expected DBLB version 1 or 2 but got {dblb_version}"));
+        }
+
+        while dblb_start + dblb_len >= reader.pos + 4 {
+            let start = reader.pos;
+            let entry_len = reader.u32()? as usize;
+            if entry_len == 0 { break }
+            let (id, data_offset) = if dblb_version == 1 {
+                reader.skip(2)?; // Type
+                let data_offset = reader.u16()? as usize;
+                (reader.u64()?, data_offset)
+            } else {
+                reader.skip(4)?;
+                let id = reader.u64()?;
+                reader.skip(2)?; // Type
+                (id, reader.u16()? as usize)
+            };
+            let name_offset = reader.u16()? as usize;
+            reader.skip(2)?;
+            if dblb_version == 1 { reader.skip(4)? }
+            let base_class = reader.u64()?;
+            if dblb_version == 2 { reader.skip(4)? }
+            reader.skip(2 + 2 + 2 + 2)?;
+            let content_offset = reader.u16()? as usize;
+            reader.skip(2)?;
+            let stream_style = reader.u8()?;
+            let fqn = reader.c_string_at(start + name_offset)?;
+
+            let compressed = data.get(start + data_offset..start + entry_len).ok_or(NodeError::Eof(start))?;
+            let mut uncompressed = vec![];
+            flate2::read::ZlibDecoder::new(compressed)
+                .read_to_end(&mut uncompressed)
+                .map_err(|e| NodeError::Format { pos: start, msg: e.to_string() })?;
+            let mut node_reader = NodeReader::new(&uncompressed);
+            node_reader.pos = content_offset;
+            if (1..=6).contains(&stream_style) {
+                node_reader.var_int()?;
+            }
+            let count = node_reader.count()?;
+            nodes.push(json!({
+                "node": {
+                    "id": id.to_string(),
+                    "fqn": fqn,
+                    "path": format!("{BUCKETS_PATH}/"),
+                    "fileName": file_name,
+                    "baseClass": base_class.to_string(),
+                },
+                "obj": node_reader.read_fields_json(count, "value")?,
+            }));
+
+            // Entries are 8 byte aligned
+            reader.pos = dblb_start + ((start - dblb_start + entry_len + 7) & !7);
+        }
+        reader.pos = dblb_start + dblb_len;
+    }
+    Ok(nodes)
+}
+
+/// File names of all buckets, from `buckets.info`
+pub fn read_bucket_names(data: &[u8]) -> Result<Vec<String>, NodeError> {
+    let mut reader = NodeReader::new(data);
+    if reader.u32()? != PBCK_MAGIC { return reader.error("expected magic PBCK") }
+    reader.skip(4)?; // Version, which jedipedia doesn't check for buckets.info either
+    let count = reader.count()?;
+    // Every name takes at least its length byte, which bounds a bad count
+    if count > data.len() - reader.pos {
+        return reader.error(format!("{count} bucket names don't fit in the file"));
+    }
+    (0..count).map(|_| {
+        let len = reader.u8()? as usize;
+        Ok(String::from_utf8_lossy(reader.bytes(len)?).into_owned())
+    }).collect()
+}
+
+/// Reads every bucket listed in `buckets.info` and keeps the nodes of these kinds, e.g. "abl".
+/// A listed bucket that's missing from the archives is an error
+pub fn read_nodes(archives: &Archives, prefixes: &[&str]) -> Result<Vec<NodeObjPair>, NodeError> {
+    read_nodes_json(archives, prefixes)?.into_iter().map(to_pair).collect()
+}
+
+/// Same as [`read_nodes`], but leaves the nodes in Jedipedia's JSON shape
+pub fn read_nodes_json(archives: &Archives, prefixes: &[&str]) -> Result<Vec<Value>, NodeError> {
+    let mut nodes = vec![];
+    for file_name in read_bucket_names(&archives.read(BUCKETS_INFO_PATH)?)? {
+        let data = archives.read(&format!("{BUCKETS_PATH}/{file_name}"))?;
+        nodes.extend(read_bucket_json(&data, &file_name)?
+            .into_iter()
+            .filter(|json| json["node"]["fqn"].as_str().is_some_and(|fqn| has_prefix(fqn, prefixes))));
+    }
+    Ok(nodes)
+}
+
+/// Whether `fqn` is a node of one of these kinds, e.g. "abl.x" is one of "abl" but not of "ab"
+pub fn has_prefix(fqn: &str, prefixes: &[&str]) -> bool {
+    prefixes.iter().any(|prefix| fqn.strip_prefix(prefix).is_some_and(|rest| rest.starts_with('.')))
+}
+
+fn to_pair(json: Value) -> Result<NodeObjPair, NodeError> {
+    NodeObjPair::deserialize(json).map_err(|e| NodeError::Format { pos: 0, msg: e.to_string() })
+}
+
+/// Reads a single prototype node by id
+pub fn read_prototype_by_id(archives: &Archives, id: u64) -> Result<NodeObjPair, NodeError> {
+    read_prototype(&archives.read(&format!("{PROTOTYPES_PATH}/{id}.node"))?)
+}
+
+/// Enums and fields of the client's GOM, the same tables GOM.json and clientGom.json have
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ClientGom {
+    /// Name and variant names by enum id
+    pub enums: BTreeMap<u64, (String, Vec<String>)>,
+    /// Name by field id
+    pub fields: BTreeMap<u64, String>,
+}
+
+/// Decodes `/resources/systemgenerated/client.gom`. Nodes, classes and associations are skipped
+pub fn read_client_gom(data: &[u8]) -> Result<ClientGom, NodeError> {
+    let mut reader = NodeReader::new(data);
+    if reader.u32()? != DBLB_MAGIC { return reader.error("expected magic DBLB") }
+    let version = reader.u32()?;
+    if version != 1 && version != 2 {
+        return reader.error(format!("expected DBLB version 1 or 2 but got {version}"));
+    }
+
+    let mut gom = ClientGom::default();
+    while reader.pos < data.len() {
+        let start = reader.pos;
+        let entry_len = reader.u32()? as usize;
+        if entry_len == 0 { break }
+        let (id, entry_type) = if version == 1 {
+            let entry_type = (reader.u16()? >> 3) & 7;
+            reader.skip(2)?; // Data offset
+            (reader.u64()?, entry_type)
+        } else {
+            reader.skip(4)?; // Unknown hash
+            let id = reader.u64()?;
+            let entry_type = (reader.u16()? >> 3) & 15;
+            reader.skip(2)?; // Data offset
+            (id, entry_type)
+        };
+        let name_offset = reader.u16()? as usize;
+        let name = reader.c_string_at(start + name_offset)?;
+        reader.skip(2)?; // Description offset
+        match entry_type {
+            2 => {
+                let count = reader.u16()? as usize;
+                reader.pos = start + reader.u16()? as usize;
+                let variants = (0..count).map(|_| {
+                    let offset = reader.u16()? as usize;
+                    reader.c_string_at(start + offset)
+                }).collect::<Result<_, _>>()?;
+                gom.enums.insert(id, (name, variants));
+            }
+            3 => { gom.fields.insert(id, name); }
+            _ => {}
+        }
+        // Entries are 8 byte aligned
+        reader.pos = (start + entry_len + 7) & !7;
+    }
+    Ok(gom)
+}
+
+pub fn read_client_gom_from(archives: &Archives) -> Result<ClientGom, NodeError> {
+    read_client_gom(&archives.read(CLIENT_GOM_PATH)?)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::io::Write;
+    use crate::archive::Archive;
+    use crate::archive::tests::write_archive;
+    use crate::parse::{ClassView, FieldValue, Id, Int, List, LookupList};
+
+    /// A version 2.5 bucket with one DBLB block, `content` is the field count followed by the fields
+    pub(crate) fn bucket(nodes: &[(u64, &str, &[u8])]) -> Vec<u8> {
+        let mut block = vec![];
+        block.extend(DBLB_MAGIC.to_le_bytes());
+        block.extend(1u32.to_le_bytes());
+        for (id, fqn, content) in nodes {
+            let mut encoder = flate2::write::ZlibEncoder::new(vec![], flate2::Compression::default());
+            encoder.write_all(content).expect("can compress");
+            let compressed = encoder.finish().expect("can compress");
+            let header_len = 45;
+            let data_offset = header_len + fqn.len() + 1;
+            block.extend(((data_offset + compressed.len()) as u32).to_le_bytes());
+            block.extend(0u16.to_le_bytes()); // Type
+            block.extend((data_offset as u16).to_le_bytes());
+            block.extend(id.to_le_bytes());
+            block.extend((header_len as u16).to_le_bytes()); // Name offset
+            block.extend([0; 2 + 4 + 8 + 8]); // Base class and unknowns
+            block.extend(0u16.to_le_bytes()); // Content offset
+            block.extend([0; 2 + 1]); // Stream style 0, no leading var int
+            block.extend(fqn.as_bytes());
+            block.push(0);
+            block.extend(compressed);
+            block.resize((block.len() + 7) & !7, 0);
+        }
+        block.extend(0u32.to_le_bytes());
+
+        let mut data = vec![];
+        data.extend(PBUK_MAGIC.to_le_bytes());
+        data.extend([2, 0, 5, 0]);
+        data.extend((block.len() as u32).to_le_bytes());
+        data.extend(block);
+        data.extend([0; 12]);
+        data
+    }
+
+    pub(crate) fn buckets_info(names: &[&str]) -> Vec<u8> {
+        let mut data = vec![];
+        data.extend(PBCK_MAGIC.to_le_bytes());
+        data.extend([1, 0, 5, 0]);
+        data.push(names.len() as u8);
+        for name in names {
+            data.push(name.len() as u8);
+            data.extend(name.as_bytes());
+        }
+        data
+    }
+
+    /// A version 2 client.gom with one entry per (id, type, name, variants), 2 is an enum and 3 a field
+    pub(crate) fn client_gom(entries: &[(u64, u16, &str, &[&str])]) -> Vec<u8> {
+        let mut data = vec![];
+        data.extend(DBLB_MAGIC.to_le_bytes());
+        data.extend(2u32.to_le_bytes());
+        for (id, entry_type, name, variants) in entries {
+            let header_len = 28;
+            let mut offsets = vec![];
+            let mut strings = vec![];
+            for variant in *variants {
+                offsets.extend(((header_len + 2 * variants.len() + strings.len()) as u16).to_le_bytes());
+                strings.extend(variant.as_bytes());
+                strings.push(0);
+            }
+            let name_offset = header_len + offsets.len() + strings.len();
+            let entry_len = name_offset + name.len() + 1;
+            let mut entry = vec![];
+            entry.extend((entry_len as u32).to_le_bytes());
+            entry.extend(0u32.to_le_bytes()); // Unknown hash
+            entry.extend(id.to_le_bytes());
+            entry.extend((entry_type << 3).to_le_bytes());
+            entry.extend(0u16.to_le_bytes()); // Data offset
+            entry.extend((name_offset as u16).to_le_bytes());
+            entry.extend(((entry_len - 1) as u16).to_le_bytes()); // Empty description
+            entry.extend((variants.len() as u16).to_le_bytes());
+            entry.extend((header_len as u16).to_le_bytes());
+            entry.extend(offsets);
+            entry.extend(strings);
+            entry.extend(name.as_bytes());
+            entry.push(0);
+            entry.resize((entry_len + 7) & !7, 0);
+            data.extend(entry);
+        }
+        data
+    }
+
+    /// Positive var int, the inverse of [`NodeReader::var_int`]
+    pub(crate) fn var_int(value: u64) -> Vec<u8> {
+        if value < 0xC0 {
+            return vec![value as u8];
+        }
+        let bytes = value.to_be_bytes();
+        let zeros = bytes.iter().take_while(|&&b| b == 0).count();
+        let mut data = vec![0xC8 + (7 - zeros) as u8];
+        data.extend(&bytes[zeros..]);
+        data
+    }
+
+    /// An archive with one uncompressed file per (path, content)
+    pub(crate) fn archive_of(name: &str, files: &[(String, Vec<u8>)]) -> Result<Archives, ArchiveError> {
+        let entries: Vec<_> = files.iter()
+            .map(|(path, content)| (path.as_str(), 0, content.as_slice(), content.len() as u32))
+            .collect();
+        Ok(Archives(vec![Archive::open(write_archive(name, 6, &entries))?]))
+    }
+
+    #[test]
+    fn read_var_int() -> Result<(), NodeError> {
+        let data = [0x05, 0xC8, 0xFF, 0xC0, 0x02, 0xCF, 0xE0, 0x00, 0xEE, 0xC0, 0xA7, 0x5A, 0x3F, 0xBB];
+        let mut reader = NodeReader::new(&data);
+        assert_eq!(VarInt { negative: false, magnitude: 5, len: 1 }, reader.var_int()?);
+        assert_eq!(VarInt { negative: false, magnitude: 255, len: 2 }, reader.var_int()?);
+        let negative = reader.var_int()?;
+        assert_eq!(VarInt { negative: true, magnitude: 2, len: 2 }, negative);
+        assert_eq!(u64::MAX - 1, negative.wrapping_u64());
+        assert_eq!(16141163575704698811, reader.var_int()?.magnitude);
+        assert!(reader.var_int().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn read_fields() -> Result<(), NodeError> {
+        let data = [
+            0xCB, 0x00, 0x00, 0x10, 0x00, 2, 0xC0, 0x1E, // id 4096: Int -30
+            0x01, 3, 0x01, // id 4097: true
+            0x02, 6, 0x03, b'a', b'b', b'c', // id 4099: "abc"
+            0x01, 7, 1, 0x02, 0x02, 0x01, 0x0A, 0x02, 0x0B, // id 4100: List [10, 11]
+            0x01, 8, 2, 3, 0x01, 0x01, 0xD2, 0x03, 0x01, // id 4101: LookupList [(3, true)]
+            0x01, 9, 7, 0x01, 0x05, 3, 0x00, // id 4102: ClassView [5: false]
+        ];
+        let mut reader = NodeReader::new(&data);
+        let fields = reader.read_fields(6)?.0;
+        assert_eq!(data.len(), reader.pos);
+        assert_eq!(vec![4096, 4097, 4099, 4100, 4101, 4102],
+                   fields.iter().map(|f| f.id.0).collect::<Vec<_>>());
+        assert_eq!(FieldValue::Int(Int(-30)), fields[0].value);
+        assert_eq!(FieldValue::Boolean(true), fields[1].value);
+        assert_eq!(FieldValue::String("abc".to_string()), fields[2].value);
+        assert_eq!(FieldValue::List(List(vec![FieldValue::Id(Id(10)), FieldValue::Id(Id(11))])), fields[3].value);
+        assert_eq!(FieldValue::LookupList(LookupList(vec![(FieldValue::Int(Int(3)), FieldValue::Boolean(true))])), fields[4].value);
+        assert_eq!(FieldValue::ClassView(ClassView(vec![Field { id: Id(5), value: FieldValue::Boolean(false) }])), fields[5].value);
+        Ok(())
+    }
+
+    #[test]
+    fn read_nodes_from_buckets() -> Result<(), NodeError> {
+        let bool_field: &[u8] = &[1, 0x01, 3, 0x01]; // id 1: true
+        let archives = archive_of("read_nodes", &[
+            (BUCKETS_INFO_PATH.to_string(), buckets_info(&["0.bkt", "1.bkt"])),
+            (format!("{BUCKETS_PATH}/0.bkt"), bucket(&[(10, "abl.test.a", bool_field), (11, "npc.test.b", bool_field)])),
+            (format!("{BUCKETS_PATH}/1.bkt"), bucket(&[(12, "tal.test.c", &[0]), (13, "ablx.test.d", &[0])])),
+        ])?;
+        let nodes = read_nodes(&archives, &["abl", "tal"])?;
+        assert_eq!(vec!["abl.test.a", "tal.test.c"], nodes.iter().map(|pair| pair.node.fqn.as_str()).collect::<Vec<_>>());
+        assert_eq!("10", nodes[0].node.id);
+        assert_eq!(vec![Field { id: Id(1), value: FieldValue::Boolean(true) }], nodes[0].obj.0);
+
+        let mut old = bucket(&[]);
+        old[6] = 3; // Version 2.3
+        assert!(matches!(read_bucket(&old, "0.bkt"), Err(NodeError::Format { pos: 8, .. })));
+        let mut info = buckets_info(&["0.bkt"]);
+        info[6] = 6; // Version 1.6
+        assert_eq!(vec!["0.bkt"], read_bucket_names(&info)?);
+
+        let missing = archive_of("read_nodes_missing", &[
+            (BUCKETS_INFO_PATH.to_string(), buckets_info(&["0.bkt", "1.bkt"])),
+            (format!("{BUCKETS_PATH}/0.bkt"), bucket(&[])),
+        ])?;
+        assert!(matches!(read_nodes(&missing, &["abl"]), Err(NodeError::Archive(ArchiveError::NotFound(_)))));
+        Ok(())
+    }
+
+    #[test]
+    fn read_gom_tables() -> Result<(), NodeError> {
+        let data = client_gom(&[
+            (7, 2, "effSlotType", &["Buff", "Debuff"]),
+            (100, 3, "effDuration", &[]),
+            (200, 4, "effEffect", &[]), // A class
+        ]);
+        let gom = read_client_gom(&data)?;
+        assert_eq!(BTreeMap::from([(7, ("effSlotType".to_string(), vec!["Buff".to_string(), "Debuff".to_string()]))]), gom.enums);
+        assert_eq!(BTreeMap::from([(100, "effDuration".to_string())]), gom.fields);
+        assert!(matches!(read_client_gom(&data[4..]), Err(NodeError::Format { pos: 4, .. })));
+        Ok(())
+    }
+}
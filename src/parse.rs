@@ -1,126 +1,61 @@
-use serde::{de::{Error, Unexpected}, Deserialize, Deserializer};
+use std::time::Duration;
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de::{Error, Unexpected}};
 use serde_json::Value;
-// use assoc::AssocExt;
 
-#[derive(Deserialize, Debug)]
+// Every error is prefixed with where it happened (node fqn, field id, list index),
+// so bad data can be found again in the json dump.
+//
+// The types read two formats. Human readable ones (JSON) are the Jedipedia shape with type
+// numbers and BigInts, anything else (the cache's MessagePack) is what they serialize to, which
+// is already parsed and skips all of that.
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Node {
     pub id: String,
     pub fqn: String,
-    pub path: String,
-    #[serde(rename = "fileName")]
-    pub file_name: String,
+    /// Id of the node's GOM class, tells apart node kinds that share a prefix like abilities and effects
+    #[serde(rename = "baseClass", default)]
+    pub base_class: String,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Field {
-    pub id: String,
-    // field_type: usize,
+    pub id: Id,
     pub value: FieldValue,
 }
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct CodeClass(pub Vec<Field>);
 
-#[derive(Deserialize, Debug)]
+#[derive(Debug, Clone)]
 pub struct NodeObjPair {
     pub node: Node,
     pub obj: CodeClass,
 }
 
-fn deserialize_with_type<'de, D>(deserializer: D, value_type: u64) -> Result<FieldValue, D::Error>
-where
-    D: Deserializer<'de>
-{
-        Ok(match value_type {
-            1 => FieldValue::Id(Int::<u64>::deserialize(deserializer)?.0),
-            2 => FieldValue::Int(Int::<i64>::deserialize(deserializer)?.0),
-            3 => FieldValue::Boolean(bool::deserialize(deserializer)?),
-            4 => FieldValue::Float(f32::deserialize(deserializer)?),
-            5 => FieldValue::Enum(Int::<u64>::deserialize(deserializer)?.0),
-            6 => FieldValue::String(String::deserialize(deserializer)?),
-            7 => { // List
-                let json: Value = Value::deserialize(deserializer)?;
-                let value_type = json.get("type").expect("type").as_u64().expect("is num");
-                let list = json.get("list").expect("list").as_array().expect("is list");
-                FieldValue::List(
-                    list.iter()
-                        .map(|val| deserialize_with_type(val, value_type))
-                        .collect::<Result<Vec<_>, _>>()
-                        .map_err(Error::custom)?)
-            },
-            8 => { // Lookuplist
-                let json: Value = Value::deserialize(deserializer)?;
-                let index_type = json.get("indexType").expect("indexType").as_u64().expect("is num");
-                let value_type = json.get("type").expect("type").as_u64().expect("is num");
-                let list = json.get("list").expect("list").as_array().expect("is list");
-                let mut map = vec![];
-                for pair in list {
-                    let key = pair.get("key").expect("key");
-                    let parsed_key = deserialize_with_type(key, index_type).map_err(Error::custom)?;
-                    let val = pair.get("val").expect("val");
-                    let parsed_val = deserialize_with_type(val, value_type).map_err(Error::custom)?;
-                    map.push((parsed_key, parsed_val));
-                }
-                FieldValue::LookupList(map)
-                    // list.iter()
-                    //     .map(|pair| {
-                    //         match (parsed_key, parsed_val) {
-                    //             (Ok(k), Ok(v)) => (k, v),
-                    //             _ => todo!(),
-                    //         }
-                    //     })
-                    //     .collect()
-            },
-            _ => FieldValue::Other(Value::deserialize(deserializer)?),
-            // _ => unimplemented!("Need to implement more types"),
-        })
+impl Serialize for NodeObjPair {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        (&self.node, &self.obj).serialize(serializer)
+    }
 }
 
-impl<'de> Deserialize<'de> for Field {
+impl<'de> Deserialize<'de> for NodeObjPair {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
-        D: Deserializer<'de>,
+        D: Deserializer<'de>
     {
+        if !deserializer.is_human_readable() {
+            let (node, obj) = Deserialize::deserialize(deserializer)?;
+            return Ok(Self { node, obj });
+        }
         let json: Value = Value::deserialize(deserializer)?;
-        let id = json.get("id").expect("id").as_str().expect("is str").to_string();
-        let value_type = json.get("type").expect("type").as_u64().expect("is num");
-        let value_str = json.get("value").expect("value");
-        let value = deserialize_with_type(value_str, value_type).map_err(Error::custom)?;
-        Ok(Self {
-            id,
-            value,
-        })
+        let node = Node::deserialize(get_key::<D::Error>(&json, "node")?).map_err(Error::custom)?;
+        let obj = CodeClass::deserialize(get_key::<D::Error>(&json, "obj")?)
+            .map_err(|e| Error::custom(format!("{}: {e}", node.fqn)))?;
+        Ok(Self { node, obj })
     }
 }
 
-// TODO: Make into SwtorType or smth
-#[derive(Debug, Clone, PartialEq)]
-pub enum FieldValue {
-    Id(u64), // type: 1, etc.
-    Int(i64),
-    Boolean(bool),
-    Float(f32),
-    Enum(u64),
-    String(String),
-    List(Vec<FieldValue>),
-    LookupList(Vec<(FieldValue, FieldValue)>),
-    Other(Value),
-    // case 9:
-    //   return 'ClassView';
-    // case 14:
-    //   return 'ScriptRef';
-    // case 15:
-    //   return 'NodeRef';
-    // case 18:
-    //   return 'Vector3';
-    // case 20:
-    //   return 'TimeInterval';
-    // case 21:
-    //   return 'Date';
-    // default:
-    //   return 'Unknown (' + type + ')'
-}
-
 #[derive(Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
 struct BigInt {
@@ -131,32 +66,34 @@ struct BigInt {
     _len: u8
 }
 
-impl From<BigInt> for u64 {
-    fn from(value: BigInt) -> Self {
-        let (high, overflowed) = (value.int_hi as u64).overflowing_shl(32);
-        assert!(value.sign == 1);
-        assert!(!overflowed);
-        high + (value.int_lo as u64)
+impl TryFrom<BigInt> for u64 {
+    type Error = String;
+
+    // Can't overflow since hi is u32. If it is too big it will overflow when that is parsed
+    fn try_from(value: BigInt) -> Result<Self, Self::Error> {
+        let high = (value.int_hi as u64) << 32;
+        if value.sign != 1 { return Err("BigInt was negative".to_string()) }
+        Ok(high + (value.int_lo as u64))
     }
 }
 
-impl From<BigInt> for i64 {
-    fn from(value: BigInt) -> Self {
-        let (high, overflowed) = (value.int_hi as i64).overflowing_shl(32);
-        assert!(!overflowed);
-        (high + (value.int_lo as i64)) * (value.sign as i64)
+impl TryFrom<BigInt> for i64 {
+    type Error = String;
+
+    fn try_from(value: BigInt) -> Result<Self, Self::Error> {
+        if value.int_hi >> 31 != 0 { return Err("BigInt overflowed".to_string()) }
+        let high = (value.int_hi as i64) << 32;
+        Ok((high + (value.int_lo as i64)) * (value.sign as i64))
     }
 }
 
-#[derive(Debug, Clone, Copy)]
-struct Int<T: IntTrait>(T);
-
-trait IntTrait: From<BigInt> {}
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Id(pub u64);
 
-impl IntTrait for u64 {}
-impl IntTrait for i64 {}
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Int(pub i64);
 
-fn unexpected(value: &Value) -> Unexpected {
+fn unexpected(value: &Value) -> Unexpected<'_> {
     match value {
         Value::Null => Unexpected::Unit,
         Value::Bool(b) => Unexpected::Bool(*b),
@@ -175,20 +112,564 @@ fn unexpected(value: &Value) -> Unexpected {
     }
 }
 
-impl<'de, T> Deserialize<'de> for Int<T>
-where
-    T: IntTrait + Deserialize<'de>
-{
+impl<'de> Deserialize<'de> for Id {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
         D: Deserializer<'de>
     {
+        if !deserializer.is_human_readable() {
+            return u64::deserialize(deserializer).map(Self);
+        }
         let value = Value::deserialize(deserializer)?;
-        Ok(Int(match value {
-            Value::Number(_) => T::deserialize(value).map_err(Error::custom)?,
-            Value::Object(_) => BigInt::deserialize(value).map_err(Error::custom)?.into(),
+        Ok(Self(match value {
+            Value::Number(_) => u64::deserialize(value).map_err(Error::custom)?,
+            Value::Object(_) => BigInt::deserialize(value).map_err(Error::custom)?.try_into().map_err(Error::custom)?,
             _ => Err(Error::invalid_type(unexpected(&value), &"BigInt or u64"))?,
         }))
     }
 }
 
+impl<'de> Deserialize<'de> for Int {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>
+    {
+        if !deserializer.is_human_readable() {
+            return i64::deserialize(deserializer).map(Self);
+        }
+        let value = Value::deserialize(deserializer)?;
+        Ok(Self(match value {
+            Value::Number(_) => i64::deserialize(value).map_err(Error::custom)?,
+            Value::Object(_) => BigInt::deserialize(value).map_err(Error::custom)?.try_into().map_err(Error::custom)?,
+            _ => Err(Error::invalid_type(unexpected(&value), &"BigInt or u64"))?,
+        }))
+    }
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct List(pub Vec<FieldValue>);
+
+fn get_key<'a, E: Error>(value: &'a Value, key: &str) -> Result<&'a Value, E> {
+    value.get(key).ok_or_else(|| Error::custom(format!("missing key \"{key}\" in {}", unexpected(value))))
+}
+
+fn value_to_u64<E: Error>(value: &Value, key: &str) -> Result<u64, E> {
+    let inner = get_key(value, key)?;
+    inner.as_u64().ok_or(Error::invalid_type(unexpected(inner), &"u64"))
+}
+
+fn value_to_array<'a, E: Error>(value: &'a Value, key: &str) -> Result<&'a Vec<Value>, E> {
+    let inner = get_key(value, key)?;
+    inner.as_array().ok_or(Error::invalid_type(unexpected(inner), &"array"))
+}
+
+impl<'de> Deserialize<'de> for List {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>
+    {
+        if !deserializer.is_human_readable() {
+            return Vec::deserialize(deserializer).map(Self);
+        }
+        let json: Value = Value::deserialize(deserializer)?;
+        let value_type = value_to_u64(&json, "type")?;
+        let list = value_to_array(&json, "list")?;
+        Ok(List(
+            list.iter()
+                .enumerate()
+                .map(|(i, val)| deserialize_with_type(val, value_type)
+                    .map_err(|e| Error::custom(format!("[{i}]: {e}"))))
+                .collect::<Result<Vec<_>, _>>()?))
+    }
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct LookupList(pub Vec<(FieldValue, FieldValue)>);
+
+impl<'de> Deserialize<'de> for LookupList {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>
+    {
+        if !deserializer.is_human_readable() {
+            return Vec::deserialize(deserializer).map(Self);
+        }
+        let json: Value = Value::deserialize(deserializer)?;
+        let index_type = value_to_u64(&json, "indexType")?;
+        let value_type = value_to_u64(&json, "type")?;
+        let list = value_to_array(&json, "list")?;
+        let mut map = vec![];
+        for (i, pair) in list.iter().enumerate() {
+            let key = get_key(pair, "key")?;
+            let parsed_key = deserialize_with_type(key, index_type)
+                .map_err(|e| Error::custom(format!("[{i}].key: {e}")))?;
+            let val = get_key(pair, "val")?;
+            let parsed_val = deserialize_with_type(val, value_type)
+                .map_err(|e| Error::custom(format!("[{i}].val: {e}")))?;
+            map.push((parsed_key, parsed_val));
+        }
+        Ok(LookupList(map))
+    }
+}
+
+// Fields of an embedded class
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ClassView(pub Vec<Field>);
+
+/// Reference to a script by id
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScriptRef(pub Id);
+
+/// Reference to another node by id, e.g. an effect node from an ability
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NodeRef(pub Id);
+
+// Stored as a whole number of milliseconds
+fn deserialize_time_interval<'de, D>(deserializer: D) -> Result<Duration, D::Error>
+where
+    D: Deserializer<'de>
+{
+    let Int(millis) = Int::deserialize(deserializer)?;
+    let millis = u64::try_from(millis).map_err(|_| Error::custom("TimeInterval was negative"))?;
+    Ok(Duration::from_millis(millis))
+}
+
+fn deserialize_with_type<'de, D>(deserializer: D, value_type: u64) -> Result<FieldValue, D::Error>
+where
+    D: Deserializer<'de>
+{
+        Ok(match value_type {
+            1 => FieldValue::Id(Id::deserialize(deserializer)?),
+            2 => FieldValue::Int(Int::deserialize(deserializer)?),
+            3 => FieldValue::Boolean(bool::deserialize(deserializer)?),
+            4 => FieldValue::Float(f32::deserialize(deserializer)?),
+            5 => FieldValue::Enum(Id::deserialize(deserializer)?),
+            6 => FieldValue::String(String::deserialize(deserializer)?),
+            7 => FieldValue::List(List::deserialize(deserializer)?),
+            8 => FieldValue::LookupList(LookupList::deserialize(deserializer)?),
+            9 => FieldValue::ClassView(ClassView::deserialize(deserializer)?),
+            14 => FieldValue::ScriptRef(ScriptRef(Id::deserialize(deserializer)?)),
+            15 => FieldValue::NodeRef(NodeRef(Id::deserialize(deserializer)?)),
+            18 => FieldValue::Vector3(<[f32; 3]>::deserialize(deserializer)?),
+            20 => FieldValue::TimeInterval(deserialize_time_interval(deserializer)?),
+            21 => FieldValue::Date(Id::deserialize(deserializer)?),
+            _ => FieldValue::Other(Value::deserialize(deserializer)?),
+            // _ => unimplemented!("Need to implement more types"),
+        })
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FieldValue {
+    Id(Id), // type: 1, etc.
+    Int(Int),
+    Boolean(bool),
+    Float(f32),
+    Enum(Id),
+    String(String),
+    List(List),
+    LookupList(LookupList),
+    ClassView(ClassView), // type: 9
+    ScriptRef(ScriptRef), // type: 14
+    NodeRef(NodeRef), // type: 15
+    Vector3([f32; 3]), // type: 18
+    TimeInterval(Duration), // type: 20
+    Date(Id), // type: 21, raw timestamp
+    Other(Value),
+}
+
+/// [`FieldValue`] as it's serialized, variant index and value without any type numbers
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "FieldValue")]
+enum CompactValue {
+    Id(Id),
+    Int(Int),
+    Boolean(bool),
+    Float(f32),
+    Enum(Id),
+    String(String),
+    List(List),
+    LookupList(LookupList),
+    ClassView(ClassView),
+    ScriptRef(ScriptRef),
+    NodeRef(NodeRef),
+    Vector3([f32; 3]),
+    TimeInterval(Duration),
+    Date(Id),
+    Other(Value),
+}
+
+impl FieldValue {
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            FieldValue::Id(_) => "Id",
+            FieldValue::Int(_) => "Int",
+            FieldValue::Boolean(_) => "Boolean",
+            FieldValue::Float(_) => "Float",
+            FieldValue::Enum(_) => "Enum",
+            FieldValue::String(_) => "String",
+            FieldValue::List(_) => "List",
+            FieldValue::LookupList(_) => "LookupList",
+            FieldValue::ClassView(_) => "ClassView",
+            FieldValue::ScriptRef(_) => "ScriptRef",
+            FieldValue::NodeRef(_) => "NodeRef",
+            FieldValue::Vector3(_) => "Vector3",
+            FieldValue::TimeInterval(_) => "TimeInterval",
+            FieldValue::Date(_) => "Date",
+            FieldValue::Other(_) => "Other",
+        }
+    }
+}
+
+impl Serialize for FieldValue {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        CompactValue::serialize(self, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for FieldValue {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>
+    {
+        if !deserializer.is_human_readable() {
+            return CompactValue::deserialize(deserializer);
+        }
+        let json: Value = Value::deserialize(deserializer)?;
+        let value_type = value_to_u64(&json, "type")?;
+        // Fields inside a ClassView call it "val"
+        let value_str = json.get("value").map_or_else(|| get_key(&json, "val"), Ok)?;
+        deserialize_with_type(value_str, value_type).map_err(Error::custom)
+    }
+}
+
+impl Serialize for Field {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        (self.id, &self.value).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Field {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>
+    {
+        if !deserializer.is_human_readable() {
+            let (id, value) = Deserialize::deserialize(deserializer)?;
+            return Ok(Self { id, value });
+        }
+        let json: Value = Value::deserialize(deserializer)?;
+        // Field ids are strings since they don't fit in a js number
+        let id = match get_key(&json, "id")? {
+            Value::String(s) => Id(s.parse().map_err(|e| Error::custom(format!("field id {s}: {e}")))?),
+            id => Id::deserialize(id).map_err(Error::custom)?,
+        };
+        let value = FieldValue::deserialize(json)
+            .map_err(|e| Error::custom(format!("field {}: {e}", id.0)))?;
+        Ok(Self { id, value })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Error;
+
+    // Add test for negative id, over- and underflow id/int
+
+    #[test]
+    fn deserialize_id() -> Result<(), Error> {
+        let data = r#"{"id":"123","type":1,"value":0}"#;
+        let expected = FieldValue::Id(Id(0));
+        assert_eq!(expected, serde_json::from_str(data)?);
+
+        let data = r#"{"id":"123","type":1,"value":{"sign":1,"intLo":0,"intHi":0,"len":1}}"#;
+        let expected = FieldValue::Id(Id(0));
+        assert_eq!(expected, serde_json::from_str(data)?);
+
+        let data = r#"{"id":"123","type":1,"value":1000}"#;
+        let expected = FieldValue::Id(Id(1000));
+        assert_eq!(expected, serde_json::from_str(data)?);
+
+        let data = r#"{"id":"123","type":1,"value":{"sign":1,"intLo":30,"intHi":0,"len":1}}"#;
+        let expected = FieldValue::Id(Id(30));
+        assert_eq!(expected, serde_json::from_str(data)?);
+
+        let data = r#"{"id":"4611686061870631196","type":1,"value":{"sign":1,"intLo":2807709627,"intHi":3758157504,"len":9}}"#;
+        let expected = FieldValue::Id(Id(16141163575704698811));
+        assert_eq!(expected, serde_json::from_str(data)?);
+
+        // Negative
+        let data = r#"{"id":"123","type":1,"value":-1000}"#;
+        assert!(serde_json::from_str::<FieldValue>(data).is_err());
+
+        let data = r#"{"id":"123","type":1,"value":{"sign":-1,"intLo":30,"intHi":0,"len":1}}"#;
+        assert!(serde_json::from_str::<FieldValue>(data).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn deserialize_int() -> Result<(), Error> {
+        let data = r#"{"id":"123","type":2,"value":0}"#;
+        let expected = FieldValue::Int(Int(0));
+        assert_eq!(expected, serde_json::from_str(data)?);
+
+        let data = r#"{"id":"123","type":2,"value":{"sign":1,"intLo":0,"intHi":0,"len":1}}"#;
+        let expected = FieldValue::Int(Int(0));
+        assert_eq!(expected, serde_json::from_str(data)?);
+
+        let data = r#"{"id":"123","type":2,"value":1000}"#;
+        let expected = FieldValue::Int(Int(1000));
+        assert_eq!(expected, serde_json::from_str(data)?);
+
+        let data = r#"{"id":"123","type":2,"value":{"sign":1,"intLo":30,"intHi":0,"len":1}}"#;
+        let expected = FieldValue::Int(Int(30));
+        assert_eq!(expected, serde_json::from_str(data)?);
+
+        let data = r#"{"id":"123","type":2,"value":-1000}"#;
+        let expected = FieldValue::Int(Int(-1000));
+        assert_eq!(expected, serde_json::from_str(data)?);
+
+        let data = r#"{"id":"123","type":2,"value":{"sign":-1,"intLo":30,"intHi":0,"len":1}}"#;
+        let expected = FieldValue::Int(Int(-30));
+        assert_eq!(expected, serde_json::from_str(data)?);
+
+        // Overflow
+        let data = r#"{"id":"4611686061870631196","type":2,"value":{"sign":1,"intLo":2807709627,"intHi":3758157504,"len":9}}"#;
+        assert!(serde_json::from_str::<FieldValue>(data).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn deserialize_bool() -> Result<(), Error> {
+        let data = r#"{"id":"123","type":3,"value":true}"#;
+        let expected = FieldValue::Boolean(true);
+        assert_eq!(expected, serde_json::from_str(data)?);
+
+        let data = r#"{"id":"123","type":3,"value":false}"#;
+        let expected = FieldValue::Boolean(false);
+        assert_eq!(expected, serde_json::from_str(data)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn deserialize_float() -> Result<(), Error> {
+        let data = r#"{"id":"123","type":4,"value":-1}"#;
+        let expected = FieldValue::Float(-1f32);
+        assert_eq!(expected, serde_json::from_str(data)?);
+
+        let data = r#"{"id":"123","type":4,"value":0}"#;
+        let expected = FieldValue::Float(0f32);
+        assert_eq!(expected, serde_json::from_str(data)?);
+
+        let data = r#"{"id":"123","type":4,"value":270}"#;
+        let expected = FieldValue::Float(270f32);
+        assert_eq!(expected, serde_json::from_str(data)?);
+
+        let data = r#"{"id":"4611686019453829664","type":4,"value":0.4000000059604645}"#;
+        let expected = FieldValue::Float(0.4f32);
+        assert_eq!(expected, serde_json::from_str(data)?);
+
+        Ok(())
+    }
+
+    // It's the same as id atm
+    // #[test]
+    // fn deserialize_enum() -> Result<(), Error> { Ok(()) }
+
+    #[test]
+    fn deserialize_string() -> Result<(), Error> {
+        let data = r#"{"id":"4611686019453829629","type":6,"value": "viciousslash"}"#;
+        let expected = FieldValue::String("viciousslash".to_string());
+        assert_eq!(expected, serde_json::from_str(data)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn deserialize_list() -> Result<(), Error> {
+        let data = r#"{
+          "id": "4611686061870631192",
+          "type": 7,
+          "value": {
+            "type": 1,
+            "list": [
+              {
+                "sign": 1,
+                "intLo": 2385807014,
+                "intHi": 3758149514,
+                "len": 9
+              },
+              {
+                "sign": 1,
+                "intLo": 2385806453,
+                "intHi": 3758149258,
+                "len": 9
+              },
+              {
+                "sign": 1,
+                "intLo": 2385808156,
+                "intHi": 3758150026,
+                "len": 9
+              }
+            ]
+          }
+        }"#;
+        let expected = FieldValue::List(List(vec![
+            FieldValue::Id(Id(16141129258494101158)),
+            FieldValue::Id(Id(16141128158982472821)),
+            FieldValue::Id(Id(16141131457517357852))
+        ]));
+        assert_eq!(expected, serde_json::from_str(data)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn deserialize_lookuplist() -> Result<(), Error> {
+        let data = r#"{
+            "id": "4611686310422994002",
+            "type": 8,
+            "value": {
+                "indexType": 1,
+                "type": 2,
+                "list": [
+                {
+                    "key": {
+                        "sign": 1,
+                        "intLo": 1195329202,
+                        "intHi": 3758117139,
+                        "len": 9
+                    },
+                    "val": {
+                        "sign": -1,
+                        "intLo": 2873223640,
+                        "intHi": 695039895,
+                        "len": 9
+                    }
+                }
+                ]
+            }
+        }"#;
+        let expected = FieldValue::LookupList(LookupList(vec![(FieldValue::Id(Id(16140990207737415346)), FieldValue::Int(Int(-2985173621313497560)))]));
+        assert_eq!(expected, serde_json::from_str(data)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn deserialize_classview() -> Result<(), Error> {
+        let data = r#"{
+            "id": "4611686019802843839",
+            "type": 9,
+            "value": [
+                {
+                    "id": "4611686019802843830",
+                    "type": 4,
+                    "val": 0.5
+                },
+                {
+                    "id": "4611686019802843835",
+                    "type": 9,
+                    "val": [
+                        {
+                            "id": "4611686019802843836",
+                            "type": 6,
+                            "val": "nested"
+                        }
+                    ]
+                }
+            ]
+        }"#;
+        let expected = FieldValue::ClassView(ClassView(vec![
+            Field { id: Id(4611686019802843830), value: FieldValue::Float(0.5) },
+            Field { id: Id(4611686019802843835), value: FieldValue::ClassView(ClassView(vec![
+                Field { id: Id(4611686019802843836), value: FieldValue::String("nested".to_string()) },
+            ])) },
+        ]));
+        assert_eq!(expected, serde_json::from_str(data)?);
+
+        let data = r#"{"id":"123","type":9,"value":[{"id":"not a number","type":3,"val":true}]}"#;
+        assert!(serde_json::from_str::<FieldValue>(data).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn deserialize_scriptref() -> Result<(), Error> {
+        let data = r#"{"id":"123","type":14,"value":{"sign":1,"intLo":2807709627,"intHi":3758157504,"len":9}}"#;
+        let expected = FieldValue::ScriptRef(ScriptRef(Id(16141163575704698811)));
+        assert_eq!(expected, serde_json::from_str(data)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn deserialize_noderef() -> Result<(), Error> {
+        let data = r#"{"id":"123","type":15,"value":{"sign":1,"intLo":2385807014,"intHi":3758149514,"len":9}}"#;
+        let expected = FieldValue::NodeRef(NodeRef(Id(16141129258494101158)));
+        assert_eq!(expected, serde_json::from_str(data)?);
+
+        let data = r#"{"id":"123","type":15,"value":{"sign":-1,"intLo":30,"intHi":0,"len":1}}"#;
+        assert!(serde_json::from_str::<FieldValue>(data).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn deserialize_vector3() -> Result<(), Error> {
+        let data = r#"{"id":"123","type":18,"value":[1,-2.5,0]}"#;
+        let expected = FieldValue::Vector3([1.0, -2.5, 0.0]);
+        assert_eq!(expected, serde_json::from_str(data)?);
+
+        let data = r#"{"id":"123","type":18,"value":[1,2]}"#;
+        assert!(serde_json::from_str::<FieldValue>(data).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn deserialize_timeinterval() -> Result<(), Error> {
+        let data = r#"{"id":"123","type":20,"value":1500}"#;
+        let expected = FieldValue::TimeInterval(Duration::from_millis(1500));
+        assert_eq!(expected, serde_json::from_str(data)?);
+
+        let data = r#"{"id":"123","type":20,"value":{"sign":1,"intLo":0,"intHi":1,"len":5}}"#;
+        let expected = FieldValue::TimeInterval(Duration::from_millis(1 << 32));
+        assert_eq!(expected, serde_json::from_str(data)?);
+
+        // Negative
+        let data = r#"{"id":"123","type":20,"value":{"sign":-1,"intLo":30,"intHi":0,"len":1}}"#;
+        assert!(serde_json::from_str::<FieldValue>(data).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn deserialize_date() -> Result<(), Error> {
+        let data = r#"{"id":"123","type":21,"value":{"sign":1,"intLo":2807709627,"intHi":30,"len":6}}"#;
+        let expected = FieldValue::Date(Id((30 << 32) + 2807709627));
+        assert_eq!(expected, serde_json::from_str(data)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn deserialize_error_path() {
+        let data = r#"{
+            "node": {"id": "1", "fqn": "abl.test.bad", "path": "", "fileName": ""},
+            "obj": [
+                {"id": "10", "type": 3, "value": true},
+                {"id": "11", "type": 7, "value": {"type": 1, "list": [1, -2]}}
+            ]
+        }"#;
+        let err = serde_json::from_str::<NodeObjPair>(data).unwrap_err().to_string();
+        assert!(err.starts_with("abl.test.bad: field 11: [1]: "), "{err}");
+
+        let data = r#"{"id":"123","type":8,"value":{"indexType":1,"type":2}}"#;
+        let err = serde_json::from_str::<FieldValue>(data).unwrap_err().to_string();
+        assert!(err.contains("missing key \"list\""), "{err}");
+    }
+}
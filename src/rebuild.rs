@@ -0,0 +1,166 @@
+// Rebuilds data/*.json, data/strings.json and the GOM tables from a game install,
+// `swtor-sim rebuild <Assets dir> [data dir]`. build.rs turns those files into the cache and the
+// generated enums and field names, so run `cargo build` afterwards to pick them up.
+
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter};
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use crate::archive::{ArchiveError, Archives};
+use crate::cache::{CLIENT_GOM_FILE, GOM_FILE, NODE_PREFIXES, STRINGS_FILE};
+use crate::node::{self, NodeError};
+use crate::parse::NodeObjPair;
+use crate::stb::{StbError, Strings, LANGUAGE};
+
+#[derive(Debug)]
+pub enum RebuildError {
+    Archive(ArchiveError),
+    Node(NodeError),
+    Stb(StbError),
+    Io { file: String, err: io::Error },
+    Json { file: String, err: serde_json::Error },
+}
+
+impl fmt::Display for RebuildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RebuildError::Archive(err) => write!(f, "{err}"),
+            RebuildError::Node(err) => write!(f, "{err}"),
+            RebuildError::Stb(err) => write!(f, "{err}"),
+            RebuildError::Io { file, err } => write!(f, "{file}: {err}"),
+            RebuildError::Json { file, err } => write!(f, "{file}: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for RebuildError {}
+
+impl From<ArchiveError> for RebuildError {
+    fn from(value: ArchiveError) -> Self {
+        RebuildError::Archive(value)
+    }
+}
+
+impl From<NodeError> for RebuildError {
+    fn from(value: NodeError) -> Self {
+        RebuildError::Node(value)
+    }
+}
+
+impl From<StbError> for RebuildError {
+    fn from(value: StbError) -> Self {
+        RebuildError::Stb(value)
+    }
+}
+
+fn write_json<T: Serialize>(path: &Path, value: &T) -> Result<(), RebuildError> {
+    let file = path.display().to_string();
+    let writer = BufWriter::new(File::create(path).map_err(|err| RebuildError::Io { file: file.clone(), err })?);
+    serde_json::to_writer(writer, value).map_err(|err| RebuildError::Json { file, err })
+}
+
+/// `Ok(None)` if the file doesn't exist
+fn read_json(path: &Path) -> Result<Option<Value>, RebuildError> {
+    let file = path.display().to_string();
+    let reader = match File::open(path) {
+        Ok(f) => BufReader::new(f),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(RebuildError::Io { file, err }),
+    };
+    serde_json::from_reader(reader).map(Some).map_err(|err| RebuildError::Json { file, err })
+}
+
+/// Adds the enums and fields of client.gom to [`GOM_FILE`] and [`CLIENT_GOM_FILE`] in `out`.
+/// Names already in the files are kept, since Jedipedia's GOM.json also names fields no HSL
+/// script uses, which client.gom leaves out
+fn write_gom(archives: &Archives, out: &Path) -> Result<(), RebuildError> {
+    let gom = node::read_client_gom_from(archives)?;
+    let gom_path = out.join(GOM_FILE);
+    let mut names = read_json(&gom_path)?.unwrap_or_else(|| json!({}));
+    let client_path = out.join(CLIENT_GOM_FILE);
+    let mut client = read_json(&client_path)?.unwrap_or_else(|| json!({}));
+    for (id, (name, variants)) in gom.enums {
+        names["enums"][id.to_string()] = json!(name);
+        client["enums"][id.to_string()] = json!(variants);
+    }
+    for (id, name) in gom.fields {
+        names["fields"][id.to_string()] = json!(name);
+    }
+    write_json(&gom_path, &names)?;
+    write_json(&client_path, &client)
+}
+
+/// Writes `{prefix}.json` to `out` for every prefix in [`NODE_PREFIXES`], the names and
+/// descriptions of those nodes to [`STRINGS_FILE`] and the GOM tables build.rs generates code
+/// from. Returns how many nodes were written
+pub fn rebuild<P: AsRef<Path>>(archives: &Archives, out: P) -> Result<usize, RebuildError> {
+    let out = out.as_ref();
+    fs::create_dir_all(out).map_err(|err| RebuildError::Io { file: out.display().to_string(), err })?;
+    let nodes = node::read_nodes_json(archives, &NODE_PREFIXES)?;
+    for prefix in NODE_PREFIXES {
+        let kind: Vec<_> = nodes.iter()
+            .filter(|json| json["node"]["fqn"].as_str().is_some_and(|fqn| node::has_prefix(fqn, &[prefix])))
+            .collect();
+        write_json(&out.join(format!("{prefix}.json")), &kind)?;
+    }
+
+    let pairs = nodes.iter()
+        .map(|json| NodeObjPair::deserialize(json).map_err(|err| RebuildError::Json { file: "buckets".to_string(), err }))
+        .collect::<Result<Vec<_>, _>>()?;
+    let objs = || pairs.iter().map(|pair| &pair.obj);
+    let strings = Strings::from_archives(archives, LANGUAGE, Strings::buckets(objs()))?;
+    write_json(&out.join(STRINGS_FILE), &strings.subset(Strings::texts(objs())))?;
+    write_gom(archives, out)?;
+    Ok(nodes.len())
+}
+
+/// Opens the archives in `assets` and rebuilds `out` from them
+pub fn rebuild_dir<P: AsRef<Path>, Q: AsRef<Path>>(assets: P, out: Q) -> Result<usize, RebuildError> {
+    rebuild(&Archives::open_dir(assets)?, out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_data::GameData;
+    use crate::node::tests::{archive_of, bucket, buckets_info, client_gom};
+    use crate::stb::LOC_NAME;
+    use crate::stb::tests::{retriever_bytes, write_stb};
+
+    #[test]
+    fn rebuild_data() -> Result<(), Box<dyn std::error::Error>> {
+        let mut named = vec![1]; // One field
+        named.extend(retriever_bytes(LOC_NAME, "str.abl", 7));
+        let archives = archive_of("rebuild", &[
+            ("/resources/systemgenerated/buckets.info".to_string(), buckets_info(&["0.bkt"])),
+            ("/resources/systemgenerated/buckets/0.bkt".to_string(), bucket(&[
+                (16140902893827567561, "abl.test.rebuilt", &named),
+                (16141067504602942620, "dis.test.rebuilt", &[0]),
+                (5, "cdx.test.ignored", &[0]),
+            ])),
+            ("/resources/en-us/str/abl.stb".to_string(), write_stb(&[(7, "Rebuilt"), (8, "Unused")])),
+            ("/resources/systemgenerated/client.gom".to_string(), client_gom(&[
+                (7, 2, "effSlotType", &["Buff", "Debuff"]),
+                (100, 3, "effDuration", &[]),
+            ])),
+        ])?;
+        let out = std::env::temp_dir().join("swtor-sim-test-rebuild");
+        fs::create_dir_all(&out)?;
+        // Fields client.gom doesn't have keep their names
+        fs::write(out.join(GOM_FILE), r#"{"fields": {"100": "old", "101": "effUnscripted"}}"#)?;
+        assert_eq!(2, rebuild(&archives, &out)?);
+        let gom = read_json(&out.join(GOM_FILE))?.unwrap();
+        assert_eq!(json!({"enums": {"7": "effSlotType"}, "fields": {"100": "effDuration", "101": "effUnscripted"}}), gom);
+        assert_eq!(json!({"enums": {"7": ["Buff", "Debuff"]}}), read_json(&out.join(CLIENT_GOM_FILE))?.unwrap());
+        let data = GameData::load(&out)?;
+        assert_eq!(2, data.len());
+        assert_eq!("abl.test.rebuilt", data.get(16140902893827567561).unwrap().node.fqn);
+        assert_eq!(Some("Rebuilt"), data.name(16140902893827567561));
+        assert_eq!(Some("dis.test.rebuilt"), data.name(16141067504602942620));
+        assert_eq!(1, data.strings().0["str.abl"].0.len());
+        fs::remove_dir_all(out)?;
+        Ok(())
+    }
+}
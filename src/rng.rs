@@ -0,0 +1,79 @@
+#![allow(dead_code)]
+
+// One seed for every random roll in a fight. Each kind of roll of each entity gets its own
+// ChaCha stream, so adding a roll somewhere doesn't shift every other roll after it.
+
+use std::collections::HashMap;
+use bevy::prelude::*;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RollKind {
+    /// Where in its damage range a hit lands
+    Damage,
+    Crit,
+    Proc,
+    /// Accuracy against defense
+    Accuracy,
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct CombatRng {
+    seed: u64,
+    streams: HashMap<(RollKind, Entity), ChaCha8Rng>,
+}
+
+impl CombatRng {
+    pub fn new(seed: u64) -> Self {
+        CombatRng { seed, streams: HashMap::new() }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn stream(&mut self, kind: RollKind, entity: Entity) -> &mut ChaCha8Rng {
+        let seed = self.seed;
+        self.streams.entry((kind, entity)).or_insert_with(|| {
+            let mut rng = ChaCha8Rng::seed_from_u64(seed);
+            rng.set_stream(u64::from(entity.index()) << 8 | kind as u64);
+            rng
+        })
+    }
+
+    /// True with probability `chance`
+    pub fn roll(&mut self, kind: RollKind, entity: Entity, chance: f64) -> bool {
+        self.stream(kind, entity).gen::<f64>() < chance
+    }
+
+    /// Uniform in [0, 1)
+    pub fn fraction(&mut self, kind: RollKind, entity: Entity) -> f64 {
+        self.stream(kind, entity).gen()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reproducible() {
+        let (a, b) = (Entity::from_raw(0), Entity::from_raw(1));
+        let rolls = |seed, kind, entity| {
+            let mut rng = CombatRng::new(seed);
+            (0..8).map(|_| rng.fraction(kind, entity)).collect::<Vec<_>>()
+        };
+        assert_eq!(rolls(1, RollKind::Crit, a), rolls(1, RollKind::Crit, a));
+        assert_ne!(rolls(1, RollKind::Crit, a), rolls(2, RollKind::Crit, a));
+        assert_ne!(rolls(1, RollKind::Crit, a), rolls(1, RollKind::Damage, a));
+        assert_ne!(rolls(1, RollKind::Crit, a), rolls(1, RollKind::Crit, b));
+
+        // Rolls of one stream don't move the others
+        let mut rng = CombatRng::new(1);
+        rng.fraction(RollKind::Damage, a);
+        assert_eq!(rolls(1, RollKind::Crit, a)[0], rng.fraction(RollKind::Crit, a));
+        assert!(rng.roll(RollKind::Proc, a, 1.0));
+        assert!(!rng.roll(RollKind::Proc, a, 0.0));
+    }
+}
@@ -0,0 +1,330 @@
+#![allow(dead_code)]
+
+// Headless fights. The app is updated in a loop without a runner, and every update jumps to the
+// next timeline event, so a fight takes as long as it takes to compute, not its length in game time.
+
+use std::collections::HashMap;
+use std::time::Duration;
+use bevy::ecs::schedule::ExecutorKind;
+use bevy::ecs::system::CommandQueue;
+use bevy::prelude::*;
+use crate::action::{run_effects, ActionPlugin, ApplyEffect, Armor, DamageDealt, DamageTaken, Defense};
+use crate::ecs_system::spawn_ability;
+use crate::effect::Effect;
+use crate::game_data::GameData;
+use crate::gom::FieldError;
+use crate::gear::{GearError, Loadout};
+use crate::diminishing_returns::DiminishingReturns;
+use crate::rng::CombatRng;
+use crate::stats::{Stats, StatsPlugin};
+use crate::talent::{TalentError, Talents};
+use crate::timeline::{advance_timeline, SimTime, Timeline, TimelineEvent, TimelinePlugin};
+use crate::trigger::{fire_triggers, AbilityActivated, CombatStarted, TriggerPlugin};
+use crate::utils::constants::{ARMOR_DEBUFF, BOSS_ARMOR, BOSS_DEFENSE};
+
+/// Default fight length, a typical dummy parse
+pub static FIGHT_LENGTH: Duration = Duration::from_secs(7 * 60);
+
+/// Abilities a caster uses in order, starting over at the end
+#[derive(Component, Debug, Clone, PartialEq)]
+pub struct Rotation {
+    pub abilities: Vec<Entity>,
+    pub target: Entity,
+    next: usize,
+}
+
+impl Rotation {
+    pub fn new(abilities: Vec<Entity>, target: Entity) -> Self {
+        Rotation { abilities, target, next: 0 }
+    }
+
+    fn advance(&mut self) -> Option<Entity> {
+        let ability = *self.abilities.get(self.next % self.abilities.len().max(1))?;
+        self.next += 1;
+        Some(ability)
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct DamageStats {
+    pub damage: f64,
+    pub hits: u32,
+    pub crits: u32,
+}
+
+impl DamageStats {
+    fn add(&mut self, amount: f64, crit: bool) {
+        self.damage += amount;
+        self.hits += 1;
+        self.crits += u32::from(crit);
+    }
+
+    /// Fraction of hits that crit, `None` without hits
+    pub fn crit_rate(&self) -> Option<f64> {
+        (self.hits > 0).then(|| f64::from(self.crits) / f64::from(self.hits))
+    }
+}
+
+/// Totals of everything the player did so far
+#[derive(Resource, Debug, Default, Clone, PartialEq)]
+struct Totals {
+    total: DamageStats,
+    by_source: HashMap<u64, DamageStats>,
+    activations: u32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimResult {
+    /// Simulated fight length
+    pub duration: Duration,
+    pub dps: f64,
+    pub total: DamageStats,
+    /// By ability id, or effect id for procs
+    pub by_source: HashMap<u64, DamageStats>,
+    pub activations: u32,
+}
+
+fn queue_rotation(mut activated: EventReader<AbilityActivated>, mut rotations: Query<&mut Rotation>, mut timeline: ResMut<Timeline>, time: Res<SimTime>) {
+    for e in activated.read() {
+        let Ok(mut rotation) = rotations.get_mut(e.caster) else {
+            continue;
+        };
+        if let Some(ability) = rotation.advance() {
+            let target = rotation.target;
+            timeline.schedule(time.0, TimelineEvent::Use { caster: e.caster, ability, target });
+        }
+    }
+}
+
+fn record(mut damage: EventReader<DamageDealt>, mut activated: EventReader<AbilityActivated>, mut totals: ResMut<Totals>, sim: Res<Combatants>) {
+    for e in damage.read().filter(|e| e.caster == sim.player && e.target == sim.boss) {
+        totals.total.add(e.amount, e.crit);
+        totals.by_source.entry(e.source).or_default().add(e.amount, e.crit);
+    }
+    totals.activations += activated.read().filter(|e| e.caster == sim.player).count() as u32;
+}
+
+#[derive(Resource, Debug, Clone, Copy)]
+struct Combatants {
+    player: Entity,
+    boss: Entity,
+}
+
+pub struct Simulation {
+    app: App,
+    player: Entity,
+    boss: Entity,
+    fight_length: Duration,
+    boss_health: Option<f64>,
+    rotation: Vec<Entity>,
+    /// Applied to the player when the fight starts
+    passives: Vec<Effect>,
+}
+
+impl Simulation {
+    pub fn new(data: GameData) -> Self {
+        let mut app = App::new();
+        app.insert_resource(data)
+           .insert_resource(CombatRng::new(0))
+           .init_resource::<Totals>()
+           .add_plugins((ActionPlugin, TriggerPlugin, TimelinePlugin, StatsPlugin))
+           .add_systems(Update, (queue_rotation.after(advance_timeline).before(run_effects), record.after(fire_triggers)));
+        let player = app.world.spawn((Name::new("player"), Stats::player())).id();
+        // Raids always bring the armor debuff
+        let boss = app.world.spawn((Name::new("boss"), DamageTaken::default(), Armor(BOSS_ARMOR * (1.0 - ARMOR_DEBUFF)), Defense(BOSS_DEFENSE))).id();
+        app.insert_resource(Combatants { player, boss });
+        Simulation { app, player, boss, fight_length: FIGHT_LENGTH, boss_health: None, rotation: vec![], passives: vec![] }
+    }
+
+    pub fn fight_length(mut self, fight_length: Duration) -> Self {
+        self.fight_length = fight_length;
+        self
+    }
+
+    /// Ends the fight once the boss has taken this much damage
+    pub fn boss_health(mut self, health: f64) -> Self {
+        self.boss_health = Some(health);
+        self
+    }
+
+    /// Seed of every random roll, the same seed always gives the same fight
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.app.insert_resource(CombatRng::new(seed));
+        self
+    }
+
+    /// Curves of the secondary stats, without them ratings give nothing
+    pub fn diminishing_returns(mut self, dr: DiminishingReturns) -> Self {
+        self.app.insert_resource(dr);
+        self
+    }
+
+    pub fn rotation(mut self, abilities: Vec<Entity>) -> Self {
+        self.rotation = abilities;
+        self
+    }
+
+    pub fn player(&self) -> Entity {
+        self.player
+    }
+
+    pub fn boss(&self) -> Entity {
+        self.boss
+    }
+
+    /// For adding components to the player or boss, or extra systems
+    pub fn app_mut(&mut self) -> &mut App {
+        &mut self.app
+    }
+
+    /// Spawns the ability with this fqn, problems with its fields are logged
+    pub fn spawn_ability(&mut self, fqn: &str) -> Option<Entity> {
+        let world = &mut self.app.world;
+        let mut queue = CommandQueue::default();
+        let (entity, problems) = {
+            let pair = world.resource::<GameData>().get_by_fqn(fqn)?;
+            spawn_ability(&mut Commands::new(&mut queue, world), pair, world.resource::<GameData>())
+        };
+        queue.apply(world);
+        // Missing fields just have their default value, anything else means the ability is wrong
+        for problem in problems {
+            match problem {
+                FieldError::Missing(_) => debug!("{fqn}: {problem}"),
+                _ => warn!("{fqn}: {problem}"),
+            }
+        }
+        Some(entity)
+    }
+
+    /// Adds the loadout's stats and weapon to the player, its set bonus is applied when the fight starts
+    pub fn equip(&mut self, loadout: &Loadout) -> Result<(), GearError> {
+        let totals = loadout.totals(self.app.world.resource::<GameData>())?;
+        let mut player = self.app.world.entity_mut(self.player);
+        if let Some(mut stats) = player.get_mut::<Stats>() {
+            totals.apply(&mut stats);
+        }
+        if let Some(weapon) = totals.weapon {
+            player.insert(weapon);
+        }
+        self.passives.extend(totals.set_bonus);
+        Ok(())
+    }
+
+    /// Gives the player these talents, on top of any it has. Passive ones are applied when the fight starts
+    pub fn learn(&mut self, ids: &[u64]) -> Result<(), TalentError> {
+        let learned = Talents::load(ids, self.app.world.resource::<GameData>())?;
+        self.passives.extend(learned.passives().cloned());
+        let mut player = self.app.world.entity_mut(self.player);
+        match player.get_mut::<Talents>() {
+            Some(mut talents) => talents.0.extend(learned.0),
+            None => { player.insert(learned); },
+        }
+        Ok(())
+    }
+
+    fn boss_dead(&self) -> bool {
+        let taken = self.app.world.get::<DamageTaken>(self.boss).map_or(0.0, |taken| taken.0);
+        self.boss_health.is_some_and(|health| taken >= health)
+    }
+
+    pub fn run(mut self) -> SimResult {
+        let (player, boss) = (self.player, self.boss);
+        let mut rotation = Rotation::new(std::mem::take(&mut self.rotation), boss);
+        if let Some(ability) = rotation.advance() {
+            self.app.world.resource_mut::<Timeline>().schedule(Duration::ZERO, TimelineEvent::Use { caster: player, ability, target: boss });
+        }
+        self.app.world.entity_mut(player).insert(rotation);
+        for effect in std::mem::take(&mut self.passives) {
+            self.app.world.send_event(ApplyEffect { caster: player, target: player, source: effect.id, effect });
+        }
+        self.app.world.send_event(CombatStarted { entity: player });
+        self.app.world.send_event(CombatStarted { entity: boss });
+
+        // Batches run many simulations at once already
+        for (_, schedule) in self.app.world.resource_mut::<Schedules>().iter_mut() {
+            schedule.set_executor_kind(ExecutorKind::SingleThreaded);
+        }
+        // Also runs startup systems when nothing is scheduled
+        self.app.update();
+        while !self.boss_dead() && self.app.world.resource::<Timeline>().next_time().is_some_and(|next| next <= self.fight_length) {
+            self.app.update();
+        }
+
+        let duration = if self.boss_dead() { self.app.world.resource::<SimTime>().0 } else { self.fight_length };
+        let totals = self.app.world.remove_resource::<Totals>().unwrap_or_default();
+        let secs = duration.as_secs_f64();
+        SimResult {
+            duration,
+            dps: if secs > 0.0 { totals.total.damage / secs } else { 0.0 },
+            total: totals.total,
+            by_source: totals.by_source,
+            activations: totals.activations,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs_system::{AbilityId, AttackType, Cooldown, Effects};
+    use crate::effect::tests::effect_node;
+    use crate::parse::FieldValue;
+    use crate::utils::constants::BASE_LEVEL_DMG;
+
+    #[test]
+    fn fight_length() {
+        let result = Simulation::new(GameData::default()).fight_length(Duration::from_secs(10)).run();
+        assert_eq!(Duration::from_secs(10), result.duration);
+        assert_eq!(0, result.activations);
+
+        let mut sim = Simulation::new(GameData::default()).fight_length(Duration::from_secs(10));
+        let instant = sim.app_mut().world.spawn((AttackType::Instant, Cooldown(0.0), AbilityId(1), Effects(vec![]))).id();
+        let result = sim.rotation(vec![instant]).run();
+        // One every GCD, at 0, 1.5, ..., 9
+        assert_eq!(7, result.activations);
+        assert_eq!(0.0, result.dps);
+    }
+
+    #[test]
+    fn boss_health() {
+        // 10% standard health per hit and no coefficient, so only crits change the damage
+        let data = GameData::from_nodes(vec![effect_node(1, &[("SpellDamage", vec![
+            ("Coefficient", FieldValue::Float(0.0)),
+            ("StandardHealthPercentMin", FieldValue::Float(0.1)),
+            ("StandardHealthPercentMax", FieldValue::Float(0.1)),
+        ])])]).unwrap();
+        let effect = Effect::load(1, &data, "test").unwrap();
+        let mut sim = Simulation::new(data).fight_length(Duration::from_secs(10)).boss_health(5000.0);
+        let boss = sim.boss();
+        sim.app_mut().world.entity_mut(boss).remove::<(Armor, Defense)>();
+        let instant = sim.app_mut().world.spawn((AttackType::Instant, Cooldown(0.0), AbilityId(1), Effects(vec![effect]))).id();
+        let result = sim.rotation(vec![instant]).run();
+
+        // Two or three hits kill it, and the fight ends with the last one instead of at 10s
+        assert!((2..=3).contains(&result.activations), "{result:?}");
+        assert_eq!(Duration::from_secs_f64(1.5 * f64::from(result.activations - 1)), result.duration);
+        assert!(result.total.damage >= 5000.0 && result.total.damage - 0.1 * BASE_LEVEL_DMG < 5000.0, "{result:?}");
+        assert_eq!(result.total.damage / result.duration.as_secs_f64(), result.dps);
+    }
+
+    fn seeded_run(seed: u64) -> SimResult {
+        let data = GameData::from_nodes(vec![effect_node(1, &[("SpellDamage", vec![
+            ("Coefficient", FieldValue::Float(1.0)),
+            ("StandardHealthPercentMin", FieldValue::Float(0.08)),
+            ("StandardHealthPercentMax", FieldValue::Float(0.12)),
+        ])])]).unwrap();
+        let effect = Effect::load(1, &data, "test").unwrap();
+        let mut sim = Simulation::new(data).fight_length(Duration::from_secs(60)).seed(seed);
+        let instant = sim.app_mut().world.spawn((AttackType::Instant, Cooldown(0.0), AbilityId(1), Effects(vec![effect]))).id();
+        sim.rotation(vec![instant]).run()
+    }
+
+    #[test]
+    fn same_seed_same_fight() {
+        let result = seeded_run(7);
+        // 40 activations with crits and misses, so every kind of roll was made
+        assert!(result.total.crits > 0 && result.total.hits < result.activations, "{result:?}");
+        assert_eq!(result, seeded_run(7));
+        assert_ne!(result, seeded_run(8));
+    }
+}
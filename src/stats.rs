@@ -0,0 +1,210 @@
+#![allow(dead_code)]
+
+// Character stats. Each stat is a base value (from the level), a bonus (gear, ModifyStat) and a
+// multiplier (class buffs). Combat only reads the derived components, which follow the stats.
+
+use bevy::prelude::*;
+use serde::Deserialize;
+use strum::{EnumCount, IntoEnumIterator};
+use crate::action::{Accuracy, BonusDamage, Crit};
+use crate::diminishing_returns::DiminishingReturns;
+use crate::timeline::{advance_timeline, Alacrity};
+use crate::utils::constants::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, strum::Display, strum::EnumCount, strum::EnumIter)]
+pub enum Stat {
+    Mastery,
+    Power,
+    Critical,
+    Alacrity,
+    Accuracy,
+    Endurance,
+    ForcePower,
+    TechPower,
+    Defense,
+    Shield,
+    Absorb,
+}
+
+impl Stat {
+    /// Stat ids, as in `statLookup` in jedipedia.js
+    pub fn id(self) -> u64 {
+        match self {
+            Stat::Mastery => 414,
+            Stat::Power => 155,
+            Stat::Critical => 156,
+            Stat::Alacrity => 255,
+            Stat::Accuracy => 154,
+            Stat::Endurance => 5,
+            Stat::ForcePower => 158,
+            Stat::TechPower => 161,
+            Stat::Defense => 163,
+            Stat::Shield => 128,
+            Stat::Absorb => 160,
+        }
+    }
+
+    pub fn from_id(id: u64) -> Option<Self> {
+        Stat::iter().find(|stat| stat.id() == id)
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct StatValue {
+    pub base: f64,
+    pub bonus: f64,
+    /// Fraction, 0.05 is +5%
+    pub multiplier: f64,
+}
+
+impl StatValue {
+    pub fn total(&self) -> f64 {
+        (self.base + self.bonus) * (1.0 + self.multiplier)
+    }
+}
+
+#[derive(Component, Debug, Default, Clone, PartialEq)]
+pub struct Stats {
+    values: [StatValue; Stat::COUNT],
+    /// Percentages from buffs on top of what the ratings give, e.g. 0.01 crit chance from a companion
+    pub extra_crit_chance: f64,
+    pub extra_crit_surge: f64,
+    pub extra_alacrity: f64,
+    pub bonus_damage_multiplier: f64,
+}
+
+impl Stats {
+    /// A max level player with all datacrons, class buffs and a companion
+    pub fn player() -> Self {
+        let mut stats = Stats {
+            extra_crit_chance: COMPANION_BUFF_CRIT_SURGE,
+            extra_crit_surge: COMPANION_BUFF_CRIT_SURGE,
+            bonus_damage_multiplier: CLASS_BUFF_BONUS_DMG,
+            ..Default::default()
+        };
+        let mastery = stats.value_mut(Stat::Mastery);
+        mastery.base = BASE_MASTERY + DATACRON_MASTERY;
+        mastery.multiplier = CLASS_BUFF_MASTERY;
+        stats
+    }
+
+    /// Adds `bonus` to a stat, e.g. from gear
+    pub fn with(mut self, stat: Stat, bonus: f64) -> Self {
+        self.value_mut(stat).bonus += bonus;
+        self
+    }
+
+    pub fn value(&self, stat: Stat) -> &StatValue {
+        &self.values[stat as usize]
+    }
+
+    pub fn value_mut(&mut self, stat: Stat) -> &mut StatValue {
+        &mut self.values[stat as usize]
+    }
+
+    pub fn get(&self, stat: Stat) -> f64 {
+        self.value(stat).total()
+    }
+
+    /// Force and tech power are the same on every item, so the bigger one is used
+    pub fn bonus_damage(&self) -> f64 {
+        let power = self.get(Stat::Power) + self.get(Stat::ForcePower).max(self.get(Stat::TechPower));
+        let bonus_dmg = self.get(Stat::Mastery) * MASTERY_DMG_BONUS + power * POWER_DMG_BONUS;
+        bonus_dmg * (1.0 + self.bonus_damage_multiplier)
+    }
+
+    pub fn crit_chance(&self, dr: &DiminishingReturns) -> f64 {
+        BASE_CRIT_CHANCE + self.extra_crit_chance + dr.percent(Stat::Critical, self.get(Stat::Critical))
+    }
+
+    /// Extra damage of a crit as a fraction, crits do `1 + crit_surge()` times the damage
+    pub fn crit_surge(&self, dr: &DiminishingReturns) -> f64 {
+        BASE_CRIT + self.extra_crit_surge + dr.percent(Stat::Critical, self.get(Stat::Critical))
+    }
+
+    pub fn crit_multiplier(&self, dr: &DiminishingReturns) -> f64 {
+        1.0 + self.crit_surge(dr)
+    }
+
+    pub fn accuracy(&self, dr: &DiminishingReturns) -> f64 {
+        BASE_ACCURACY + dr.percent(Stat::Accuracy, self.get(Stat::Accuracy))
+    }
+
+    pub fn alacrity(&self, dr: &DiminishingReturns) -> f64 {
+        self.extra_alacrity + dr.percent(Stat::Alacrity, self.get(Stat::Alacrity))
+    }
+}
+
+/// Keeps the components combat uses in line with `Stats`
+pub fn derive_stats(
+    mut commands: Commands,
+    dr: Res<DiminishingReturns>,
+    stats: Query<(Entity, Ref<Stats>)>,
+) {
+    for (entity, stats) in &stats {
+        if !stats.is_changed() && !dr.is_changed() {
+            continue;
+        }
+        commands.entity(entity).insert((
+            BonusDamage(stats.bonus_damage()),
+            Crit { chance: stats.crit_chance(&dr), surge: stats.crit_surge(&dr) },
+            Alacrity(stats.alacrity(&dr)),
+            Accuracy(stats.accuracy(&dr)),
+        ));
+    }
+}
+
+pub struct StatsPlugin;
+
+impl Plugin for StatsPlugin {
+    fn build(&self, app: &mut App) {
+        // Changes from effects apply from the next timeline step on
+        app.init_resource::<DiminishingReturns>()
+           .add_systems(Update, derive_stats.before(advance_timeline));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diminishing_returns::DrCurve;
+    use crate::timeline::scaled;
+
+    fn dr() -> DiminishingReturns {
+        DiminishingReturns {
+            level: LEVEL,
+            curves: [
+                (Stat::Critical, DrCurve { cap: 0.3, magic: 2.41 }),
+                (Stat::Alacrity, DrCurve { cap: 0.3, magic: 3.2 }),
+            ].into(),
+        }
+    }
+
+    #[test]
+    fn layers() {
+        let mut stats = Stats::default().with(Stat::Power, 100.0).with(Stat::Power, 50.0);
+        stats.value_mut(Stat::Power).base = 50.0;
+        stats.value_mut(Stat::Power).multiplier = 0.1;
+        assert_eq!(220.0, stats.get(Stat::Power).round());
+        assert_eq!(Some(Stat::Mastery), Stat::from_id(414));
+        assert_eq!(None, Stat::from_id(1));
+    }
+
+    #[test]
+    fn derived() {
+        let stats = Stats::player().with(Stat::Critical, 655.0).with(Stat::Mastery, 1223.0).with(Stat::Power, 940.0);
+        let dr = dr();
+        let crit = 0.3 * (1.0 - (1.0f64 - 0.01 / 0.3).powf(655.0 / LEVEL / 2.41));
+        assert_eq!(BASE_CRIT + COMPANION_BUFF_CRIT_SURGE + crit, stats.crit_surge(&dr));
+        assert_eq!(BASE_CRIT_CHANCE + COMPANION_BUFF_CRIT_SURGE + crit, stats.crit_chance(&dr));
+        assert_eq!(BASE_CRIT_CHANCE + COMPANION_BUFF_CRIT_SURGE, stats.crit_chance(&DiminishingReturns::default()));
+        let mastery = (BASE_MASTERY + DATACRON_MASTERY + 1223.0) * (1.0 + CLASS_BUFF_MASTERY);
+        let bonus_dmg = (mastery * MASTERY_DMG_BONUS + 940.0 * POWER_DMG_BONUS) * (1.0 + CLASS_BUFF_BONUS_DMG);
+        assert!((bonus_dmg - stats.bonus_damage()).abs() < 1e-9);
+
+        // Alacrity works perfectly, >2054 makes all gcds 1.4
+        let alacrity = Stats::default().with(Stat::Alacrity, 2054.0).alacrity(&dr);
+        assert!(scaled(GCD, alacrity).as_secs_f64() <= 1.4);
+        assert!(scaled(GCD, Stats::default().with(Stat::Alacrity, 2000.0).alacrity(&dr)).as_secs_f64() > 1.4);
+    }
+}
@@ -0,0 +1,296 @@
+#![allow(dead_code)]
+
+// String tables (.stb), following `readSTB` and `loadStb` in jedipedia.js.
+// Nodes don't contain any text. Names and descriptions are (bucket, id) pairs in the node's
+// loc text retriever map, pointing into /resources/<language>/str/<bucket>.stb, e.g. str.abl.
+
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+use crate::archive::{ArchiveError, Archives};
+use crate::cache::StringMap;
+use crate::gom::{FieldError, Fields};
+use crate::parse::{CodeClass, FieldValue, Id, Int};
+
+pub static LANGUAGE: &str = "en-us";
+/// Loc text retriever map of a node, by field id as in `node_convertLocRetriever` in jedipedia.js
+static LOC_TEXT_RETRIEVER_MAP_ID: u64 = 4611686102842470023;
+static LOC_STRING_ID: u64 = 4611686093000569992;
+static LOC_BUCKET_ID: u64 = 4611686093000569993;
+/// Keys in the retriever map
+pub static LOC_NAME: u64 = 15685385242400905286;
+pub static LOC_DESCRIPTION: u64 = 2806211896052149513;
+
+static HEADER_SIZE: usize = 7;
+static ENTRY_SIZE: usize = 26;
+
+#[derive(Debug)]
+pub enum StbError {
+    Eof(usize),
+    LengthMismatch { index: usize, len: u32, len2: u32 },
+    Archive(ArchiveError),
+}
+
+impl fmt::Display for StbError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StbError::Eof(pos) => write!(f, "unexpected end of string table at {pos}"),
+            StbError::LengthMismatch { index, len, len2 } => write!(f, "string {index} has lengths {len} and {len2}"),
+            StbError::Archive(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl std::error::Error for StbError {}
+
+impl From<ArchiveError> for StbError {
+    fn from(value: ArchiveError) -> Self {
+        StbError::Archive(value)
+    }
+}
+
+fn u32_at(data: &[u8], pos: usize) -> Result<u32, StbError> {
+    data.get(pos..pos + 4)
+        .map(|bytes| u32::from_le_bytes(bytes.try_into().expect("slice is 4 bytes")))
+        .ok_or(StbError::Eof(pos))
+}
+
+/// One .stb file, by string id
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StringTable(pub HashMap<u64, String>);
+
+impl StringTable {
+    pub fn get(&self, id: u64) -> Option<&str> {
+        self.0.get(&id).map(String::as_str)
+    }
+}
+
+/// Decodes a string table. Ids can appear more than once (female variants), different
+/// strings with the same id are joined with newlines like `loadStb` does
+pub fn read_stb(data: &[u8]) -> Result<StringTable, StbError> {
+    let count = u32_at(data, 3)? as usize;
+    // A bad count fails at the first missing entry, don't allocate for it
+    let mut strings: HashMap<u64, String> = HashMap::with_capacity(count.min(data.len() / ENTRY_SIZE));
+    for index in 0..count {
+        let pos = HEADER_SIZE + index * ENTRY_SIZE;
+        let id = u64::from(u32_at(data, pos)?) | u64::from(u32_at(data, pos + 4)?) << 32;
+        // Bitflag (u16) and version (f32) in between
+        let len = u32_at(data, pos + 14)?;
+        let offset = u32_at(data, pos + 18)? as usize;
+        let len2 = u32_at(data, pos + 22)?;
+        if len != len2 {
+            return Err(StbError::LengthMismatch { index, len, len2 });
+        }
+        let bytes = data.get(offset..offset + len as usize).ok_or(StbError::Eof(offset))?;
+        let string = String::from_utf8_lossy(bytes);
+        if string.is_empty() {
+            continue;
+        }
+        match strings.get_mut(&id) {
+            None => { strings.insert(id, string.into_owned()); },
+            Some(existing) if !existing.split('\n').any(|s| s == string) => {
+                existing.push('\n');
+                existing.push_str(&string);
+            },
+            Some(_) => {},
+        }
+    }
+    Ok(StringTable(strings))
+}
+
+/// Where a node's text is, e.g. bucket "str.abl"
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LocText {
+    pub bucket: String,
+    pub id: u64,
+}
+
+// Ids past i64::MAX can be stored as negative Ints
+fn id_of(value: &FieldValue) -> Option<u64> {
+    match value {
+        FieldValue::Id(Id(id)) => Some(*id),
+        FieldValue::Int(Int(int)) => Some(*int as u64),
+        _ => None,
+    }
+}
+
+/// Text of a node under a retriever map key, e.g. [`LOC_NAME`]. `Ok(None)` if it has none
+pub fn loc_text(obj: &CodeClass, key: u64) -> Result<Option<LocText>, FieldError> {
+    let field = format!("{LOC_TEXT_RETRIEVER_MAP_ID}");
+    let map = match obj.get_by_id(LOC_TEXT_RETRIEVER_MAP_ID) {
+        None => return Ok(None),
+        Some(FieldValue::LookupList(map)) => map,
+        Some(other) => return Err(FieldError::WrongType { field, expected: "LookupList", found: other.type_name() }),
+    };
+    let Some((_, value)) = map.0.iter().find(|(k, _)| id_of(k) == Some(key)) else {
+        return Ok(None);
+    };
+    let FieldValue::ClassView(class) = value else {
+        return Err(FieldError::WrongType { field: format!("{field}[{key}]"), expected: "ClassView", found: value.type_name() });
+    };
+    let id = class.get_by_id(LOC_STRING_ID).and_then(id_of)
+        .ok_or_else(|| FieldError::invalid(&field, format!("[{key}] has no string id")))?;
+    let bucket = match class.get_by_id(LOC_BUCKET_ID) {
+        Some(FieldValue::String(bucket)) => bucket.clone(),
+        _ => return Err(FieldError::invalid(&field, format!("[{key}] has no bucket"))),
+    };
+    Ok(Some(LocText { bucket, id }))
+}
+
+/// Every loaded string table, by bucket
+#[derive(Debug, Clone, Default)]
+pub struct Strings(pub HashMap<String, StringTable>);
+
+impl Strings {
+    /// Names and descriptions of the nodes
+    pub fn texts<'a>(objs: impl IntoIterator<Item = &'a CodeClass> + 'a) -> impl Iterator<Item = LocText> + 'a {
+        objs.into_iter()
+            .flat_map(|obj| [LOC_NAME, LOC_DESCRIPTION].map(|key| loc_text(obj, key)))
+            .filter_map(|text| text.ok().flatten())
+    }
+
+    /// Buckets the nodes' names and descriptions are in
+    pub fn buckets<'a>(objs: impl IntoIterator<Item = &'a CodeClass> + 'a) -> BTreeSet<String> {
+        Self::texts(objs).map(|text| text.bucket).collect()
+    }
+
+    /// Only the strings of these texts, which is what the cache keeps instead of whole tables
+    pub fn subset(&self, texts: impl IntoIterator<Item = LocText>) -> StringMap {
+        let mut map = StringMap::new();
+        for text in texts {
+            if let Some(string) = self.get(&text) {
+                map.entry(text.bucket).or_default().insert(text.id, string.to_string());
+            }
+        }
+        map
+    }
+
+    /// Reads the buckets from the game files in `language`, e.g. "en-us"
+    pub fn from_archives<I, S>(archives: &Archives, language: &str, buckets: I) -> Result<Self, StbError>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut strings = Strings::default();
+        for bucket in buckets {
+            let bucket = bucket.as_ref();
+            let path = format!("/resources/{language}/{}.stb", bucket.replace('.', "/"));
+            strings.0.insert(bucket.to_string(), read_stb(&archives.read(&path)?)?);
+        }
+        Ok(strings)
+    }
+
+    /// Strings as stored in the cache and data/strings.json
+    pub fn from_map(map: StringMap) -> Self {
+        Strings(map.into_iter().map(|(bucket, table)| (bucket, StringTable(table))).collect())
+    }
+
+    pub fn get(&self, text: &LocText) -> Option<&str> {
+        self.0.get(&text.bucket)?.get(text.id)
+    }
+
+    /// Localized text of a node under a retriever map key, `None` if it has none or it isn't loaded
+    pub fn text(&self, obj: &CodeClass, key: u64) -> Option<&str> {
+        loc_text(obj, key).ok().flatten().and_then(|text| self.get(&text))
+    }
+
+    pub fn name(&self, obj: &CodeClass) -> Option<&str> {
+        self.text(obj, LOC_NAME)
+    }
+
+    pub fn description(&self, obj: &CodeClass) -> Option<&str> {
+        self.text(obj, LOC_DESCRIPTION)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::node::tests::var_int;
+    use crate::parse::{ClassView, Field, LookupList};
+
+    /// String table with these (id, string) entries
+    pub(crate) fn write_stb(entries: &[(u64, &str)]) -> Vec<u8> {
+        let mut data = vec![1, 0, 0];
+        data.extend((entries.len() as u32).to_le_bytes());
+        let mut offset = HEADER_SIZE + entries.len() * ENTRY_SIZE;
+        for (id, string) in entries {
+            data.extend((*id as u32).to_le_bytes());
+            data.extend(((id >> 32) as u32).to_le_bytes());
+            data.extend(0u16.to_le_bytes());
+            data.extend(1f32.to_le_bytes());
+            data.extend((string.len() as u32).to_le_bytes());
+            data.extend((offset as u32).to_le_bytes());
+            data.extend((string.len() as u32).to_le_bytes());
+            offset += string.len();
+        }
+        for (_, string) in entries {
+            data.extend(string.as_bytes());
+        }
+        data
+    }
+
+    /// A node field with these (retriever key, bucket, string id) texts
+    pub(crate) fn retriever(texts: &[(u64, &str, u64)]) -> Field {
+        let list = texts.iter().map(|&(key, bucket, id)| (
+            FieldValue::Id(Id(key)),
+            FieldValue::ClassView(ClassView(vec![
+                Field { id: Id(LOC_STRING_ID), value: FieldValue::Id(Id(id)) },
+                Field { id: Id(LOC_BUCKET_ID), value: FieldValue::String(bucket.to_string()) },
+            ])),
+        )).collect();
+        Field { id: Id(LOC_TEXT_RETRIEVER_MAP_ID), value: FieldValue::LookupList(LookupList(list)) }
+    }
+
+    /// The same retriever field encoded as in a node, id offset from 0
+    pub(crate) fn retriever_bytes(key: u64, bucket: &str, id: u64) -> Vec<u8> {
+        let mut data = var_int(LOC_TEXT_RETRIEVER_MAP_ID);
+        data.extend([8, 1, 9, 1, 1]); // LookupList from Id to ClassView, count 1 twice
+        data.extend(var_int(key));
+        data.extend([0, 2]); // Unknown byte, 2 fields
+        data.extend(var_int(LOC_STRING_ID));
+        data.push(1);
+        data.extend(var_int(id));
+        data.extend(var_int(LOC_BUCKET_ID - LOC_STRING_ID));
+        data.extend([6, bucket.len() as u8]);
+        data.extend(bucket.as_bytes());
+        data
+    }
+
+    #[test]
+    fn read() -> Result<(), StbError> {
+        let high = 3_758_096_384u64 << 32;
+        let table = read_stb(&write_stb(&[
+            (high | 1, "Rail Shot"),
+            (high | 2, "Deals damage."),
+            (high | 2, "Deals damage."),
+            (high | 3, "He"),
+            (high | 3, "She"),
+            (4, ""),
+        ]))?;
+        assert_eq!(Some("Rail Shot"), table.get(high | 1));
+        assert_eq!(Some("Deals damage."), table.get(high | 2));
+        assert_eq!(Some("He\nShe"), table.get(high | 3));
+        assert_eq!(None, table.get(4));
+
+        let mut bad = write_stb(&[(1, "abc")]);
+        bad[HEADER_SIZE + 22] = 4;
+        assert!(matches!(read_stb(&bad), Err(StbError::LengthMismatch { index: 0, .. })));
+        assert!(matches!(read_stb(&bad[..10]), Err(StbError::Eof(_))));
+        Ok(())
+    }
+
+    #[test]
+    fn node_texts() -> Result<(), StbError> {
+        let obj = CodeClass(vec![retriever(&[(LOC_NAME, "str.abl", 1), (LOC_DESCRIPTION, "str.abl", 2)])]);
+        assert_eq!(Ok(Some(LocText { bucket: "str.abl".to_string(), id: 1 })), loc_text(&obj, LOC_NAME));
+        assert_eq!(Ok(None), loc_text(&CodeClass(vec![]), LOC_NAME));
+        assert_eq!(BTreeSet::from(["str.abl".to_string()]), Strings::buckets([&obj]));
+
+        let mut strings = Strings::default();
+        assert_eq!(None, strings.name(&obj));
+        strings.0.insert("str.abl".to_string(), read_stb(&write_stb(&[(1, "Rail Shot"), (2, "Deals damage.")]))?);
+        assert_eq!(Some("Rail Shot"), strings.name(&obj));
+        assert_eq!(Some("Deals damage."), strings.description(&obj));
+        Ok(())
+    }
+}
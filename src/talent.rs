@@ -0,0 +1,355 @@
+#![allow(dead_code)]
+
+// Talents. A tal.* node changes stats of the player or of one ability. Its stat data is read with
+// the layout jedipedia.js renders guild perks with (`mapTalentStat`), which talents are assumed to
+// share. Talents that add procs or buffs are passive abilities instead, their effects are applied
+// when the fight starts like the set bonus.
+// Everything is kept on the player's `Talents` component and looked up when an ability is used.
+
+use std::fmt;
+use bevy::prelude::*;
+use crate::effect::{ability_effects, key_of, Effect};
+use crate::game_data::GameData;
+use crate::gom::{FieldError, Fields};
+use crate::parse::{ClassView, FieldValue};
+
+/// Stat data of a talent, by field id as in the guild perk renderer of jedipedia.js
+static TAL_STAT_DATA_ID: u64 = 4611686296951520009;
+static TAL_DEFENSIVE_STATS_ID: u64 = 4611686296951520005;
+static TAL_OFFENSIVE_STATS_ID: u64 = 4611686296951520006;
+static TAL_STAT_ID: u64 = 4611686296951520002;
+static TAL_STAT_VALUE_ID: u64 = 4611686296951520003;
+static TAL_STAT_MODIFIER_ID: u64 = 4611686296951520010;
+static TAL_STAT_ENABLED_ID: u64 = 4611686297385060000;
+static TAL_STAT_TARGET_ID: u64 = 4611686346859110000;
+// Stat ids, see `statLookup` in jedipedia.js
+static STAT_COOLDOWN_TIME: u64 = 64;
+static STAT_DAMAGE_DONE_PERCENT: u64 = 79;
+static STAT_CRIT_CHANCE_PERCENT: u64 = 147;
+static STAT_CRIT_HIT_CHANCE: u64 = 361;
+static STAT_CRIT_HIT_DAMAGE: u64 = 362;
+// Values of a stat's modifier. Which one is which is a guess until it's checked against tal nodes,
+// stats without one of these are kept as `Modifier::Stat` rather than guessing what they mean
+static STAT_MODIFIER_FLAT: u64 = 1;
+static STAT_MODIFIER_PERCENT: u64 = 2;
+
+#[derive(Debug)]
+pub enum TalentError {
+    UnknownNode(u64),
+    /// Neither a tal.* nor an abl.* node
+    NotTalent(String),
+    Field { talent: String, err: FieldError },
+}
+
+impl fmt::Display for TalentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TalentError::UnknownNode(id) => write!(f, "no talent node {id}"),
+            TalentError::NotTalent(fqn) => write!(f, "{fqn} isn't a talent"),
+            TalentError::Field { talent, err } => write!(f, "{talent}: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for TalentError {}
+
+/// What a talent changes. `ability` is the ability node it's limited to, `None` for all of them
+#[derive(Debug, Clone, PartialEq)]
+pub enum Modifier {
+    /// Seconds added to the cooldown, negative to shorten it
+    Cooldown { ability: Option<u64>, seconds: f64 },
+    /// Cooldown changed by a fraction, -0.1 for 10% shorter
+    CooldownPercent { ability: Option<u64>, percent: f64 },
+    /// Damage done as a fraction, 0.05 for 5% more
+    Damage { ability: Option<u64>, percent: f64 },
+    /// Damage added to every hit, before `Damage` multiplies it
+    FlatDamage { ability: Option<u64>, amount: f64 },
+    CritChance { ability: Option<u64>, chance: f64 },
+    CritSurge { ability: Option<u64>, surge: f64 },
+    /// Applied to the player when the fight starts, procs come with their triggers
+    Passive(Effect),
+    /// Any other stat, by stat id
+    Stat { stat: u64, value: f64, modifier: Option<u64>, ability: Option<u64> },
+}
+
+impl Modifier {
+    /// The modifier says whether the value is flat or a percentage.
+    /// Stats without a modifier, or with one this doesn't know, are kept as `Stat`
+    fn from_stat(stat: u64, value: f64, modifier: Option<u64>, ability: Option<u64>) -> Self {
+        let percent = match modifier {
+            Some(modifier) if modifier == STAT_MODIFIER_FLAT => false,
+            Some(modifier) if modifier == STAT_MODIFIER_PERCENT => true,
+            _ => return Modifier::Stat { stat, value, modifier, ability },
+        };
+        match stat {
+            _ if stat == STAT_COOLDOWN_TIME && percent => Modifier::CooldownPercent { ability, percent: value },
+            _ if stat == STAT_COOLDOWN_TIME => Modifier::Cooldown { ability, seconds: value },
+            _ if stat == STAT_DAMAGE_DONE_PERCENT && !percent => Modifier::FlatDamage { ability, amount: value },
+            _ if stat == STAT_DAMAGE_DONE_PERCENT => Modifier::Damage { ability, percent: value },
+            _ if stat == STAT_CRIT_CHANCE_PERCENT || stat == STAT_CRIT_HIT_CHANCE => Modifier::CritChance { ability, chance: value },
+            _ if stat == STAT_CRIT_HIT_DAMAGE => Modifier::CritSurge { ability, surge: value },
+            _ => Modifier::Stat { stat, value, modifier, ability },
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Talent {
+    pub id: u64,
+    pub fqn: String,
+    pub modifiers: Vec<Modifier>,
+}
+
+/// One entry of a talent's stat data
+fn talent_stat(class: &ClassView, field: &str) -> Result<Modifier, FieldError> {
+    let stat = class.get_by_id(TAL_STAT_ID).and_then(key_of)
+        .ok_or_else(|| FieldError::invalid(field, "has no stat"))?;
+    let value = class.get_by_id(TAL_STAT_VALUE_ID).and_then(FieldValue::as_number).unwrap_or(0.0);
+    let modifier = class.get_by_id(TAL_STAT_MODIFIER_ID).and_then(key_of);
+    let ability = class.get_by_id(TAL_STAT_TARGET_ID).and_then(key_of);
+    if !matches!(class.get_by_id(TAL_STAT_ENABLED_ID), Some(FieldValue::Boolean(true))) {
+        warn!("{field}: stat {stat} isn't enabled");
+    }
+    let modifier = Modifier::from_stat(stat, value, modifier, ability);
+    if let Modifier::Stat { modifier, .. } = modifier {
+        warn!("{field}: stat {stat} with modifier {modifier:?} isn't applied");
+    }
+    Ok(modifier)
+}
+
+/// Modifiers of a tal.* node, defensive ones first
+pub fn talent_stats<F: Fields + ?Sized>(talent: &F) -> Result<Vec<Modifier>, FieldError> {
+    let field = format!("{TAL_STAT_DATA_ID}");
+    let data = match talent.get_by_id(TAL_STAT_DATA_ID) {
+        None => return Ok(vec![]),
+        Some(FieldValue::List(list)) => list,
+        Some(other) => return Err(FieldError::WrongType { field, expected: "List", found: other.type_name() }),
+    };
+    let mut modifiers = vec![];
+    for (i, class) in data.0.iter().enumerate() {
+        let FieldValue::ClassView(class) = class else {
+            return Err(FieldError::WrongType { field: format!("{field}[{i}]"), expected: "ClassView", found: class.type_name() });
+        };
+        for list_id in [TAL_DEFENSIVE_STATS_ID, TAL_OFFENSIVE_STATS_ID] {
+            let field = format!("{field}[{i}].{list_id}");
+            let entries = match class.get_by_id(list_id) {
+                None => continue,
+                Some(FieldValue::List(entries)) => entries,
+                Some(other) => return Err(FieldError::WrongType { field, expected: "List", found: other.type_name() }),
+            };
+            for (j, entry) in entries.0.iter().enumerate() {
+                let field = format!("{field}[{j}]");
+                let FieldValue::ClassView(entry) = entry else {
+                    return Err(FieldError::WrongType { field, expected: "ClassView", found: entry.type_name() });
+                };
+                modifiers.push(talent_stat(entry, &field)?);
+            }
+        }
+    }
+    Ok(modifiers)
+}
+
+impl Talent {
+    /// A tal.* node, or a passive abl.* node whose effects become `Modifier::Passive`
+    pub fn load(id: u64, data: &GameData) -> Result<Self, TalentError> {
+        let pair = data.get(id).ok_or(TalentError::UnknownNode(id))?;
+        let fqn = pair.node.fqn.clone();
+        let field_err = |err| TalentError::Field { talent: fqn.clone(), err };
+        let modifiers = if fqn.starts_with("tal.") {
+            talent_stats(&pair.obj).map_err(field_err)?
+        } else if fqn.starts_with("abl.") {
+            let (effects, _) = ability_effects(&pair.obj, data).map_err(field_err)?;
+            effects.into_iter().map(Modifier::Passive).collect()
+        } else {
+            return Err(TalentError::NotTalent(fqn));
+        };
+        Ok(Talent { id, fqn, modifiers })
+    }
+}
+
+/// Chosen talents of a player
+#[derive(Component, Debug, Clone, Default, PartialEq)]
+pub struct Talents(pub Vec<Talent>);
+
+fn applies(target: &Option<u64>, ability: u64) -> bool {
+    target.map_or(true, |target| target == ability)
+}
+
+impl Talents {
+    pub fn load(ids: &[u64], data: &GameData) -> Result<Self, TalentError> {
+        ids.iter().map(|&id| Talent::load(id, data)).collect::<Result<_, _>>().map(Talents)
+    }
+
+    pub fn modifiers(&self) -> impl Iterator<Item = &Modifier> {
+        self.0.iter().flat_map(|talent| &talent.modifiers)
+    }
+
+    pub fn passives(&self) -> impl Iterator<Item = &Effect> {
+        self.modifiers().filter_map(|modifier| match modifier {
+            Modifier::Passive(effect) => Some(effect),
+            _ => None,
+        })
+    }
+
+    /// Damage of `ability` is multiplied by this
+    pub fn damage_multiplier(&self, ability: u64) -> f64 {
+        1.0 + self.modifiers().map(|modifier| match modifier {
+            Modifier::Damage { ability: target, percent } if applies(target, ability) => *percent,
+            _ => 0.0,
+        }).sum::<f64>()
+    }
+
+    /// Damage added to every hit of `ability`
+    pub fn flat_damage(&self, ability: u64) -> f64 {
+        self.modifiers().map(|modifier| match modifier {
+            Modifier::FlatDamage { ability: target, amount } if applies(target, ability) => *amount,
+            _ => 0.0,
+        }).sum()
+    }
+
+    /// Cooldown in seconds of `ability` whose own cooldown is `base`, flat changes first
+    pub fn cooldown(&self, ability: u64, base: f64) -> f64 {
+        let (seconds, percent) = self.modifiers().fold((0.0, 0.0), |(seconds, percent), modifier| match modifier {
+            Modifier::Cooldown { ability: target, seconds: s } if applies(target, ability) => (seconds + s, percent),
+            Modifier::CooldownPercent { ability: target, percent: p } if applies(target, ability) => (seconds, percent + p),
+            _ => (seconds, percent),
+        });
+        (base + seconds) * (1.0 + percent)
+    }
+
+    /// Crit chance added to `ability`
+    pub fn crit_chance(&self, ability: u64) -> f64 {
+        self.modifiers().map(|modifier| match modifier {
+            Modifier::CritChance { ability: target, chance } if applies(target, ability) => *chance,
+            _ => 0.0,
+        }).sum()
+    }
+
+    /// Crit surge added to `ability`
+    pub fn crit_surge(&self, ability: u64) -> f64 {
+        self.modifiers().map(|modifier| match modifier {
+            Modifier::CritSurge { ability: target, surge } if applies(target, ability) => *surge,
+            _ => 0.0,
+        }).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::action::apply_effect;
+    use crate::effect::ABL_EFFECT_IDS;
+    use crate::effect::tests::effect_node;
+    use crate::game_data::ABILITY_BASE_CLASS;
+    use crate::game_data::tests::{field, node};
+    use crate::parse::{Field, Id, List, NodeObjPair, NodeRef};
+    use crate::stats::{Stat, Stats};
+
+    fn id_field(id: u64, value: FieldValue) -> Field {
+        Field { id: Id(id), value }
+    }
+
+    fn stat(stat: u64, value: f32, target: Option<u64>) -> FieldValue {
+        let mut fields = vec![
+            id_field(TAL_STAT_ID, FieldValue::Id(Id(stat))),
+            id_field(TAL_STAT_VALUE_ID, FieldValue::Float(value)),
+            id_field(TAL_STAT_ENABLED_ID, FieldValue::Boolean(true)),
+        ];
+        if let Some(target) = target {
+            fields.push(id_field(TAL_STAT_TARGET_ID, FieldValue::NodeRef(NodeRef(Id(target)))));
+        }
+        FieldValue::ClassView(ClassView(fields))
+    }
+
+    fn modified(stat: FieldValue, modifier: u64) -> FieldValue {
+        let FieldValue::ClassView(mut class) = stat else {
+            unreachable!("stat() is a ClassView");
+        };
+        class.0.push(id_field(TAL_STAT_MODIFIER_ID, FieldValue::Enum(Id(modifier))));
+        FieldValue::ClassView(class)
+    }
+
+    fn percent(stat: FieldValue) -> FieldValue {
+        modified(stat, STAT_MODIFIER_PERCENT)
+    }
+
+    fn flat(stat: FieldValue) -> FieldValue {
+        modified(stat, STAT_MODIFIER_FLAT)
+    }
+
+    fn talent(id: u64, fqn: &str, defensive: Vec<FieldValue>, offensive: Vec<FieldValue>) -> NodeObjPair {
+        let data = ClassView(vec![
+            id_field(TAL_DEFENSIVE_STATS_ID, FieldValue::List(List(defensive))),
+            id_field(TAL_OFFENSIVE_STATS_ID, FieldValue::List(List(offensive))),
+        ]);
+        node(id, fqn, vec![id_field(TAL_STAT_DATA_ID, FieldValue::List(List(vec![FieldValue::ClassView(data)])))])
+    }
+
+    #[test]
+    fn load() -> Result<(), Box<dyn std::error::Error>> {
+        let data = GameData::from_nodes(vec![
+            talent(1, "tal.sith_inquisitor.darkness.dark_charge", vec![stat(43, 0.6, None)], vec![percent(stat(79, -0.1, None))]),
+            talent(2, "tal.sith_inquisitor.darkness.trashing_blades", vec![], vec![percent(stat(79, 0.05, Some(10))), flat(stat(64, -3.0, Some(10)))]),
+            talent(3, "tal.sith_inquisitor.darkness.crit", vec![], vec![percent(stat(147, 0.1, Some(11))), percent(stat(362, 0.3, Some(11)))]),
+            talent(4, "itm.gen.lightsaber", vec![], vec![]),
+            talent(5, "tal.sith_inquisitor.darkness.modified", vec![], vec![
+                percent(stat(64, -0.25, Some(12))),
+                flat(stat(79, 100.0, Some(12))),
+                modified(stat(79, 1.0, Some(12)), 7),
+                stat(79, 1.0, Some(12)),
+            ]),
+        ])?;
+        let talents = Talents::load(&[1, 2, 3, 5], &data)?;
+        assert_eq!(Modifier::Stat { stat: 43, value: 0.6000000238418579, modifier: None, ability: None }, talents.0[0].modifiers[0]);
+
+        // Made up like the old PASSIVE_MODS of Dark Charge + Trashing Blades, not read from their nodes
+        assert!((talents.damage_multiplier(10) - 0.95).abs() < 1e-6);
+        assert!((talents.damage_multiplier(11) - 0.9).abs() < 1e-6);
+        assert_eq!(7.0, talents.cooldown(10, 10.0));
+        assert_eq!(10.0, talents.cooldown(11, 10.0));
+        assert!((talents.crit_chance(11) - 0.1).abs() < 1e-6);
+        assert!((talents.crit_surge(11) - 0.3).abs() < 1e-6);
+        assert_eq!(0.0, talents.crit_surge(10));
+        assert_eq!(0, talents.passives().count());
+
+        // The modifier makes cooldowns a percentage and damage flat, unknown or no modifiers are kept as is
+        assert_eq!(7.5, talents.cooldown(12, 10.0));
+        assert_eq!(100.0, talents.flat_damage(12));
+        assert_eq!(0.0, talents.flat_damage(10));
+        assert!((talents.damage_multiplier(12) - 0.9).abs() < 1e-6);
+        assert_eq!(Modifier::Stat { stat: 79, value: 1.0, modifier: Some(7), ability: Some(12) }, talents.0[3].modifiers[2]);
+        assert_eq!(Modifier::Stat { stat: 79, value: 1.0, modifier: None, ability: Some(12) }, talents.0[3].modifiers[3]);
+
+        assert!(matches!(Talents::load(&[4], &data), Err(TalentError::NotTalent(_))));
+        assert!(matches!(Talents::load(&[6], &data), Err(TalentError::UnknownNode(6))));
+        Ok(())
+    }
+
+    #[test]
+    fn passive() -> Result<(), Box<dyn std::error::Error>> {
+        // A passive ability lists the effId of its effect, not the effect node's id
+        let (ability_id, eff_id) = (16140902893827567561, 16141176512342836547);
+        let mut effect = effect_node(eff_id, &[("ModifyStat", vec![
+            ("Stat", FieldValue::Id(Id(Stat::Power.id()))),
+            ("Value", FieldValue::Float(100.0)),
+        ])]);
+        effect.node.id = "16140902893827567562".to_string();
+        let mut passive = node(ability_id, "abl.sith_inquisitor.darkness.passive", vec![
+            field("ablIsPassive", FieldValue::Boolean(true)),
+            field(ABL_EFFECT_IDS, FieldValue::List(List(vec![FieldValue::Id(Id(eff_id))]))),
+        ]);
+        passive.node.base_class = ABILITY_BASE_CLASS.to_string();
+        let data = GameData::from_nodes(vec![passive, effect])?;
+
+        let talents = Talents::load(&[ability_id], &data)?;
+        let passives: Vec<_> = talents.passives().collect();
+        assert_eq!(vec![&Effect::load(eff_id, &data, "test")?], passives);
+
+        // Applied to the player when the fight starts, it's where the power comes from
+        let mut world = World::new();
+        let player = world.spawn(Stats::default()).id();
+        assert!(apply_effect(&mut world, player, player, ability_id, passives[0]).is_empty());
+        assert_eq!(100.0, world.get::<Stats>(player).unwrap().get(Stat::Power));
+        Ok(())
+    }
+}
@@ -0,0 +1,306 @@
+#![allow(dead_code)]
+
+// Discrete-event scheduler. Every `Update` jumps simulated time to the next scheduled event,
+// so a fight takes as many updates as it has events, however long it is in game time.
+// Events at the same time run in the order they were scheduled, which keeps runs deterministic.
+
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+use std::time::Duration;
+use bevy::prelude::*;
+use crate::action::{apply_effect, expire_effects, ActiveEffects, ApplyEffect};
+use crate::ecs_system::{AbilityId, AttackType, Cooldown, Effects};
+use crate::effect::{Effect, EffectSlot};
+use crate::talent::Talents;
+use crate::trigger::AbilityActivated;
+use crate::utils::constants::{CHANNEL_TICK, GCD};
+
+/// Time since the simulation started
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq)]
+pub struct SimTime(pub Duration);
+
+/// Alacrity of a caster as a fraction, e.g. 0.07 for 7%.
+/// Alacrity works perfectly, so it scales the GCD, cast, channel and cooldown times exactly
+#[derive(Component, Debug, Default, Clone, Copy, PartialEq)]
+pub struct Alacrity(pub f64);
+
+/// When a caster's global cooldown and current cast or channel end
+#[derive(Component, Debug, Default, Clone, Copy, PartialEq)]
+pub struct CasterState {
+    pub gcd_end: Duration,
+    pub busy_end: Duration,
+}
+
+/// The cooldown of an ability, as the debuff it puts on its caster. It has the ability's id,
+/// which no effect node shares, and lasts the cooldown after talents and alacrity
+pub fn cooldown_effect(ability: u64, duration: Duration) -> Effect {
+    Effect { id: ability, slot: EffectSlot::Debuff, duration, ..Default::default() }
+}
+
+/// When the cooldown of `ability` ends, if the caster with `active` effects has it on cooldown
+fn cooldown_end(active: &ActiveEffects, ability: u64, now: Duration) -> Option<Duration> {
+    active.0.iter()
+          .find(|active| active.effect == ability && active.slot == EffectSlot::Debuff)
+          .map(|active| active.expires)
+          .filter(|&expires| expires > now)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TimelineEvent {
+    /// Use an ability as soon as the caster's GCD, cast and the ability's cooldown allow it
+    Use { caster: Entity, ability: Entity, target: Entity },
+    /// A cast finished, so the ability's effects land
+    CastFinished { caster: Entity, ability: Entity, target: Entity },
+    /// A channel ticked, so the ability's effects land again
+    ChannelTick { caster: Entity, ability: Entity, target: Entity },
+}
+
+#[derive(Debug)]
+struct Scheduled {
+    at: Duration,
+    seq: u64,
+    event: TimelineEvent,
+}
+
+impl PartialEq for Scheduled {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Scheduled {}
+
+impl PartialOrd for Scheduled {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Scheduled {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.at, self.seq).cmp(&(other.at, other.seq))
+    }
+}
+
+#[derive(Resource, Debug, Default)]
+pub struct Timeline {
+    queue: BinaryHeap<Reverse<Scheduled>>,
+    next_seq: u64,
+}
+
+impl Timeline {
+    pub fn schedule(&mut self, at: Duration, event: TimelineEvent) {
+        self.queue.push(Reverse(Scheduled { at, seq: self.next_seq, event }));
+        self.next_seq += 1;
+    }
+
+    pub fn next_time(&self) -> Option<Duration> {
+        self.queue.peek().map(|Reverse(scheduled)| scheduled.at)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    /// Removes every event at the earliest time, in the order they were scheduled
+    fn pop_next(&mut self) -> Option<(Duration, Vec<TimelineEvent>)> {
+        let at = self.next_time()?;
+        let mut events = vec![];
+        while self.next_time() == Some(at) {
+            events.push(self.queue.pop().expect("was just peeked").0.event);
+        }
+        Some((at, events))
+    }
+}
+
+/// `secs` shortened by alacrity
+pub fn scaled(secs: f64, alacrity: f64) -> Duration {
+    Duration::from_secs_f64(secs / (1.0 + alacrity))
+}
+
+/// Sends `ApplyEffect` for every effect of `ability`
+fn land(world: &mut World, caster: Entity, ability: Entity, target: Entity) {
+    let Some(ability_ref) = world.get_entity(ability) else {
+        return;
+    };
+    let source = ability_ref.get::<AbilityId>().map_or(0, |id| id.0);
+    let effects = ability_ref.get::<Effects>().map_or(vec![], |effects| effects.0.clone());
+    for effect in effects {
+        world.send_event(ApplyEffect { caster, target, source, effect });
+    }
+}
+
+fn use_ability(world: &mut World, now: Duration, caster: Entity, ability: Entity, target: Entity) {
+    let Some(ability_ref) = world.get_entity(ability) else {
+        warn!("ability {ability:?} doesn't exist");
+        return;
+    };
+    let attack_type = match ability_ref.get::<AttackType>() {
+        Some(AttackType::Instant) | None => None,
+        Some(AttackType::Cast(t)) => Some((true, *t)),
+        Some(AttackType::Channel(t)) => Some((false, *t)),
+    };
+    let cooldown = ability_ref.get::<Cooldown>().map_or(0.0, |cooldown| cooldown.0);
+    let id = ability_ref.get::<AbilityId>().map_or(0, |id| id.0);
+
+    let Some(mut caster_mut) = world.get_entity_mut(caster) else {
+        warn!("caster {caster:?} doesn't exist");
+        return;
+    };
+    let alacrity = caster_mut.get::<Alacrity>().copied().unwrap_or_default().0;
+    let cooldown = caster_mut.get::<Talents>().map_or(cooldown, |talents| talents.cooldown(id, cooldown)).max(0.0);
+    let state = caster_mut.get::<CasterState>().copied().unwrap_or_default();
+    let cooldown_end = caster_mut.get::<ActiveEffects>()
+                                 .and_then(|active| cooldown_end(active, id, now))
+                                 .unwrap_or_default();
+    let ready = state.gcd_end.max(state.busy_end).max(cooldown_end);
+    if ready > now {
+        world.resource_mut::<Timeline>().schedule(ready, TimelineEvent::Use { caster, ability, target });
+        return;
+    }
+
+    let busy_end = now + attack_type.map_or(Duration::ZERO, |(_, t)| scaled(t, alacrity));
+    caster_mut.insert(CasterState { gcd_end: now + scaled(GCD, alacrity), busy_end });
+    if cooldown > 0.0 {
+        for problem in apply_effect(world, caster, caster, id, &cooldown_effect(id, scaled(cooldown, alacrity))) {
+            warn!("cooldown of {id}: {problem}");
+        }
+    }
+
+    world.send_event(AbilityActivated { caster, target, ability: id });
+    match attack_type {
+        Some((true, _)) => world.resource_mut::<Timeline>().schedule(busy_end, TimelineEvent::CastFinished { caster, ability, target }),
+        Some((false, t)) => {
+            land(world, caster, ability, target);
+            schedule_ticks(&mut world.resource_mut::<Timeline>(), now, t, alacrity, caster, ability, target);
+        }
+        None => land(world, caster, ability, target),
+    }
+}
+
+/// Schedules a tick every [`CHANNEL_TICK`] of a `secs` long channel after the one at `now`, so a
+/// 3s channel ticks 4 times. Ticks share their time with anything else scheduled then and run
+/// after it, which is what lines them up with DoT ticks for double ticks
+fn schedule_ticks(timeline: &mut Timeline, now: Duration, secs: f64, alacrity: f64,
+                  caster: Entity, ability: Entity, target: Entity) {
+    let ticks = (secs / CHANNEL_TICK + 1e-9).floor() as u32;
+    for tick in 1..=ticks {
+        timeline.schedule(now + scaled(CHANNEL_TICK * tick as f64, alacrity), TimelineEvent::ChannelTick { caster, ability, target });
+    }
+}
+
+/// Moves simulated time to the next scheduled events and runs them
+pub fn advance_timeline(world: &mut World) {
+    let Some((now, events)) = world.resource_mut::<Timeline>().pop_next() else {
+        return;
+    };
+    world.resource_mut::<SimTime>().0 = now;
+    for event in events {
+        match event {
+            TimelineEvent::Use { caster, ability, target } => use_ability(world, now, caster, ability, target),
+            TimelineEvent::CastFinished { caster, ability, target }
+            | TimelineEvent::ChannelTick { caster, ability, target } => land(world, caster, ability, target),
+        }
+    }
+}
+
+/// Updates `app` until nothing is scheduled before `end`
+pub fn run_until(app: &mut App, end: Duration) {
+    while app.world.resource::<Timeline>().next_time().is_some_and(|next| next <= end) {
+        app.update();
+    }
+}
+
+pub struct TimelinePlugin;
+
+impl Plugin for TimelinePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SimTime>()
+           .init_resource::<Timeline>()
+           .add_systems(Update, advance_timeline.before(expire_effects));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::talent::{Modifier, Talent};
+
+    /// Activation times in ms of three abilities queued at once: an instant with a 6s cooldown,
+    /// a 2s cast, then the instant again
+    fn activations(alacrity: f64, talents: Talents) -> Vec<(u128, u64)> {
+        let mut world = World::new();
+        world.init_resource::<SimTime>();
+        world.init_resource::<Timeline>();
+        world.init_resource::<Events<ApplyEffect>>();
+        world.init_resource::<Events<AbilityActivated>>();
+        let caster = world.spawn((Alacrity(alacrity), talents)).id();
+        let target = world.spawn_empty().id();
+        let instant = world.spawn((AttackType::Instant, Cooldown(6.0), AbilityId(1), Effects(vec![]))).id();
+        let cast = world.spawn((AttackType::Cast(2.0), Cooldown(0.0), AbilityId(2), Effects(vec![]))).id();
+        for ability in [instant, cast, instant] {
+            world.resource_mut::<Timeline>().schedule(Duration::ZERO, TimelineEvent::Use { caster, ability, target });
+        }
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems((advance_timeline, expire_effects).chain());
+        let mut activations = vec![];
+        while !world.resource::<Timeline>().is_empty() {
+            schedule.run(&mut world);
+            let now = world.resource::<SimTime>().0.as_millis();
+            activations.extend(world.resource_mut::<Events<AbilityActivated>>().drain().map(|e| (now, e.ability)));
+        }
+        activations
+    }
+
+    #[test]
+    fn gcd_casts_and_cooldowns() {
+        assert_eq!(vec![(0, 1), (1500, 2), (6000, 1)], activations(0.0, Talents::default()));
+        assert_eq!(vec![(0, 1), (1200, 2), (4800, 1)], activations(0.25, Talents::default()));
+
+        let talent = Talent { id: 3, fqn: "tal.test".to_string(), modifiers: vec![Modifier::Cooldown { ability: Some(1), seconds: -2.0 }] };
+        assert_eq!(vec![(0, 1), (1500, 2), (4000, 1)], activations(0.0, Talents(vec![talent])));
+    }
+
+    /// Times in ms at which a 3s channel's effect lands
+    fn channel_landings(alacrity: f64) -> Vec<u128> {
+        let mut world = World::new();
+        world.init_resource::<SimTime>();
+        world.init_resource::<Timeline>();
+        world.init_resource::<Events<ApplyEffect>>();
+        world.init_resource::<Events<AbilityActivated>>();
+        let caster = world.spawn(Alacrity(alacrity)).id();
+        let target = world.spawn_empty().id();
+        let effect = Effect { id: 1, ..Default::default() };
+        let channel = world.spawn((AttackType::Channel(3.0), Cooldown(0.0), AbilityId(1), Effects(vec![effect]))).id();
+        world.resource_mut::<Timeline>().schedule(Duration::ZERO, TimelineEvent::Use { caster, ability: channel, target });
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems(advance_timeline);
+        let mut landings = vec![];
+        while !world.resource::<Timeline>().is_empty() {
+            schedule.run(&mut world);
+            let now = world.resource::<SimTime>().0.as_millis();
+            landings.extend(world.resource_mut::<Events<ApplyEffect>>().drain().map(|_| now));
+        }
+        landings
+    }
+
+    #[test]
+    fn channel_ticks() {
+        assert_eq!(vec![0, 1000, 2000, 3000], channel_landings(0.0));
+        assert_eq!(vec![0, 800, 1600, 2400], channel_landings(0.25));
+    }
+
+    #[test]
+    fn same_time_order() {
+        let mut timeline = Timeline::default();
+        let event = |n| TimelineEvent::Use { caster: Entity::from_raw(n), ability: Entity::from_raw(0), target: Entity::from_raw(0) };
+        timeline.schedule(Duration::from_secs(2), event(0));
+        timeline.schedule(Duration::from_secs(1), event(1));
+        timeline.schedule(Duration::from_secs(1), event(2));
+        assert_eq!(Some((Duration::from_secs(1), vec![event(1), event(2)])), timeline.pop_next());
+        assert_eq!(Some((Duration::from_secs(2), vec![event(0)])), timeline.pop_next());
+        assert_eq!(None, timeline.pop_next());
+    }
+}
@@ -0,0 +1,380 @@
+#![allow(dead_code)]
+
+// Ability tooltips. Descriptions have tokens like `<<1>>` that the game fills with numbers from
+// the ability's effects and the character's stats, e.g. "Deals <<1>> weapon damage".
+// What each token stands for is in the ability node's description token list. The layout of
+// that list is read by GOM name and hasn't been checked against every ability yet, so tokens that
+// can't be filled are left in the tooltip and reported with it instead of failing the whole tooltip.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+use crate::action::{SpellDamage, WeaponDamage};
+use crate::effect::{ability_effects, gom_name, ActionName, Effect, GomName};
+use crate::game_data::GameData;
+use crate::gom::{default_if_missing, FieldError, Fields};
+use crate::parse::{FieldValue, NodeObjPair};
+use crate::stats::Stats;
+use crate::talent::Talents;
+
+static ABL_DESCRIPTION_TOKENS: &str = "ablDescriptionTokens";
+static ABL_DESCRIPTION_TOKEN_TYPE: &str = "ablDescriptionTokenType";
+static ABL_DESCRIPTION_TOKEN_MULTIPLIER: &str = "ablDescriptionTokenMultiplier";
+static ABL_DESCRIPTION_TOKEN_EFFECT_INDEX: &str = "ablDescriptionTokenEffectIndex";
+static ABL_DESCRIPTION_TOKEN_ACTION_INDEX: &str = "ablDescriptionTokenActionIndex";
+static ABL_DESCRIPTION_TOKEN_VALUE: &str = "ablDescriptionTokenValue";
+
+#[derive(Debug)]
+pub enum TooltipError {
+    Field(FieldError),
+    NoDescription(String),
+    /// The description has a token the ability doesn't define
+    UnknownToken(u32),
+    NoEffect { token: u32, effect: usize },
+    NoDamage { token: u32, effect: usize, action: usize },
+    Syntax(String),
+}
+
+impl fmt::Display for TooltipError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TooltipError::Field(e) => write!(f, "{e}"),
+            TooltipError::NoDescription(fqn) => write!(f, "{fqn} has no description loaded"),
+            TooltipError::UnknownToken(token) => write!(f, "token <<{token}>> isn't defined"),
+            TooltipError::NoEffect { token, effect } => write!(f, "token <<{token}>> uses effect {effect}, which doesn't exist"),
+            TooltipError::NoDamage { token, effect, action } => write!(f, "token <<{token}>> uses damage {action} of effect {effect}, which doesn't exist"),
+            TooltipError::Syntax(msg) => write!(f, "bad token: {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for TooltipError {}
+
+impl From<FieldError> for TooltipError {
+    fn from(value: FieldError) -> Self {
+        TooltipError::Field(value)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, strum::EnumString)]
+pub enum TokenType {
+    #[strum(serialize = "ablDescriptionTokenTypeDamage")]
+    Damage,
+    #[strum(serialize = "ablDescriptionTokenTypeDuration")]
+    Duration,
+    #[strum(serialize = "ablDescriptionTokenTypeFloat")]
+    Float,
+    #[strum(default)]
+    Other(String),
+}
+
+impl GomName for TokenType {
+    const ENUM: &'static str = "ablDescriptionTokenType";
+}
+
+/// What a description token stands for. Effects are indexed like `ablEffectIDs`
+#[derive(Debug, Clone, PartialEq)]
+pub enum Token {
+    /// Range of the `action`th SpellDamage of an effect, times `multiplier` (e.g. the number of ticks)
+    Damage { effect: usize, action: usize, multiplier: f64 },
+    /// Duration of an effect in seconds
+    Duration { effect: usize },
+    Value(f64),
+}
+
+fn index<F: Fields + ?Sized>(obj: &F, field: &str) -> Result<usize, FieldError> {
+    let index = default_if_missing(obj.get_int(field))?;
+    usize::try_from(index).map_err(|_| FieldError::invalid(field, format!("{index} is negative")))
+}
+
+impl Token {
+    pub fn from_fields<F: Fields + ?Sized>(obj: &F) -> Result<Self, FieldError> {
+        let float = |field, default| default_if_missing(obj.get_float(field).map(Some)).map(|v| v.map_or(default, f64::from));
+        Ok(match gom_name(obj, ABL_DESCRIPTION_TOKEN_TYPE)? {
+            TokenType::Damage => Token::Damage {
+                effect: index(obj, ABL_DESCRIPTION_TOKEN_EFFECT_INDEX)?,
+                action: index(obj, ABL_DESCRIPTION_TOKEN_ACTION_INDEX)?,
+                multiplier: float(ABL_DESCRIPTION_TOKEN_MULTIPLIER, 1.0)?,
+            },
+            TokenType::Duration => Token::Duration { effect: index(obj, ABL_DESCRIPTION_TOKEN_EFFECT_INDEX)? },
+            TokenType::Float => Token::Value(float(ABL_DESCRIPTION_TOKEN_VALUE, 0.0)?),
+            TokenType::Other(name) => return Err(FieldError::invalid(ABL_DESCRIPTION_TOKEN_TYPE, format!("unsupported token type {name}"))),
+        })
+    }
+}
+
+/// Description tokens of an ability node, by number
+#[derive(Debug, Default)]
+pub struct DescriptionTokens {
+    pub tokens: BTreeMap<u32, Token>,
+    /// Tokens that couldn't be read, e.g. of a type that isn't supported
+    pub unread: BTreeMap<u32, FieldError>,
+}
+
+pub fn description_tokens<F: Fields + ?Sized>(ability: &F) -> Result<DescriptionTokens, FieldError> {
+    let mut tokens = DescriptionTokens::default();
+    for (key, value) in default_if_missing(ability.get_lookup(ABL_DESCRIPTION_TOKENS))? {
+        let number = key.as_int()
+            .and_then(|n| u32::try_from(n).ok())
+            .ok_or_else(|| FieldError::invalid(ABL_DESCRIPTION_TOKENS, format!("token number {key:?}")))?;
+        let token = match value {
+            FieldValue::ClassView(class) => Token::from_fields(class).map_err(|e| e.within(&format!("{ABL_DESCRIPTION_TOKENS}[{number}]"))),
+            other => Err(FieldError::WrongType { field: format!("{ABL_DESCRIPTION_TOKENS}[{number}]"), expected: "ClassView", found: other.type_name() }),
+        };
+        match token {
+            Ok(token) => { tokens.tokens.insert(number, token); },
+            Err(err) => { tokens.unread.insert(number, err); },
+        }
+    }
+    Ok(tokens)
+}
+
+/// Whole numbers without decimals, everything else with up to two
+fn format_number(value: f64) -> String {
+    let rounded = format!("{value:.2}");
+    rounded.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+/// Picks the plural form of `[zero/one/many]` or `[one/many]` and fills in `%d`
+fn plural(forms: &str, value: f64, formatted: &str) -> String {
+    let forms: Vec<_> = forms.split('/').collect();
+    let form = match forms[..] {
+        [zero, _, _] if value == 0.0 => zero,
+        [_, one, _] | [one, _] if value == 1.0 => one,
+        [.., many] => many,
+        [] => unreachable!("split always returns something"),
+    };
+    form.replace("%d", formatted)
+}
+
+/// A rendered description. Tokens in `problems` are left as they are in `text`
+#[derive(Debug)]
+pub struct Tooltip {
+    pub text: String,
+    pub problems: Vec<TooltipError>,
+}
+
+/// What a tooltip is rendered for
+#[derive(Debug, Clone, Copy)]
+pub struct TooltipInput<'a> {
+    pub stats: &'a Stats,
+    pub weapon: Option<&'a WeaponDamage>,
+    /// Their damage modifiers are in the tooltip, like the game shows them
+    pub talents: Option<&'a Talents>,
+}
+
+impl TooltipInput<'_> {
+    /// Lowest to highest hit of `ability`, or one number if there's no range
+    fn damage(&self, ability: u64, damage: &SpellDamage, multiplier: f64) -> (String, f64) {
+        let bonus_dmg = self.stats.bonus_damage();
+        let multiplier = multiplier * self.talents.map_or(1.0, |talents| talents.damage_multiplier(ability));
+        let flat_damage = self.talents.map_or(0.0, |talents| talents.flat_damage(ability));
+        let [min, max] = [0.0, 1.0].map(|roll| ((damage.hit(bonus_dmg, self.weapon, roll) + flat_damage) * multiplier).round());
+        if min == max {
+            (format_number(min), min)
+        } else {
+            (format!("{}-{}", format_number(min), format_number(max)), max)
+        }
+    }
+
+    /// Text of a token and the number used for plural forms
+    fn value(&self, ability: u64, number: u32, token: &Token, effects: &[Effect]) -> Result<(String, f64), TooltipError> {
+        let effect = |effect: usize| effects.get(effect).ok_or(TooltipError::NoEffect { token: number, effect });
+        Ok(match *token {
+            Token::Damage { effect: i, action, multiplier } => {
+                let damage = effect(i)?.sub_effects.iter()
+                    .flat_map(|sub_effect| &sub_effect.actions)
+                    .filter(|a| a.name == ActionName::SpellDamage)
+                    .nth(action)
+                    .ok_or(TooltipError::NoDamage { token: number, effect: i, action })?;
+                self.damage(ability, &SpellDamage::from_params(&damage.params)?, multiplier)
+            },
+            Token::Duration { effect: i } => {
+                let secs = effect(i)?.duration.as_secs_f64();
+                (format_number(secs), secs)
+            },
+            Token::Value(value) => (format_number(value), value),
+        })
+    }
+
+    /// Fills in every `<<n>>` and `<<n[forms]>>` token of the description of `ability`.
+    /// Only a description that can't be split into tokens is an error
+    pub fn render(&self, ability: u64, description: &str, tokens: &BTreeMap<u32, Token>, effects: &[Effect]) -> Result<Tooltip, TooltipError> {
+        let mut out = String::with_capacity(description.len());
+        let mut problems = vec![];
+        let mut rest = description;
+        while let Some(start) = rest.find("<<") {
+            out.push_str(&rest[..start]);
+            let end = rest[start..].find(">>")
+                .ok_or_else(|| TooltipError::Syntax(format!("unclosed token in {:?}", &rest[start..])))?;
+            let inner = &rest[start + 2..start + end];
+            let (number, forms) = match inner.split_once('[') {
+                Some((number, forms)) => (number, Some(forms.strip_suffix(']')
+                    .ok_or_else(|| TooltipError::Syntax(format!("unclosed [ in <<{inner}>>")))?)),
+                None => (inner, None),
+            };
+            let number = u32::from_str(number).map_err(|_| TooltipError::Syntax(format!("<<{inner}>>")))?;
+            let value = tokens.get(&number)
+                .ok_or(TooltipError::UnknownToken(number))
+                .and_then(|token| self.value(ability, number, token, effects));
+            match (value, forms) {
+                (Ok((text, value)), Some(forms)) => out.push_str(&plural(forms, value, &text)),
+                (Ok((text, _)), None) => out.push_str(&text),
+                (Err(err), _) => {
+                    out.push_str(&rest[start..start + end + 2]);
+                    problems.push(err);
+                },
+            }
+            rest = &rest[start + end + 2..];
+        }
+        out.push_str(rest);
+        Ok(Tooltip { text: out, problems })
+    }
+
+    /// Tooltip of an ability node, the description comes from the loaded strings
+    pub fn ability(&self, pair: &NodeObjPair, data: &GameData) -> Result<Tooltip, TooltipError> {
+        let description = data.strings().description(&pair.obj)
+            .ok_or_else(|| TooltipError::NoDescription(pair.node.fqn.clone()))?;
+        let DescriptionTokens { tokens, mut unread } = description_tokens(&pair.obj)?;
+        let (effects, _) = ability_effects(&pair.obj, data)?;
+        // GameData only holds nodes with numeric ids
+        let id = pair.node.id.parse().unwrap_or_default();
+        let mut tooltip = self.render(id, description, &tokens, &effects)?;
+        // Tokens that couldn't be read are reported with the reason, not as undefined
+        for problem in &mut tooltip.problems {
+            if let TooltipError::UnknownToken(number) = problem {
+                if let Some(err) = unread.remove(number) {
+                    *problem = TooltipError::Field(err);
+                }
+            }
+        }
+        tooltip.problems.extend(unread.into_values().map(TooltipError::Field));
+        Ok(tooltip)
+    }
+
+    /// Tooltips of every ability, by fqn, to compare against the game
+    pub fn all(&self, data: &GameData) -> BTreeMap<String, Result<Tooltip, TooltipError>> {
+        data.abilities().map(|pair| (pair.node.fqn.clone(), self.ability(pair, data))).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::action::DamageType;
+    use std::time::Duration;
+    use crate::effect::{EffAct, SubEffect, ABL_EFFECT_IDS, EFF_DURATION};
+    use crate::effect::tests::effect_node;
+    use crate::game_data::ABILITY_BASE_CLASS;
+    use crate::game_data::tests::{field, node};
+    use crate::types::variant_value;
+    use crate::parse::{ClassView, Id, Int, List, LookupList};
+    use crate::stats::Stat;
+    use crate::stb::{read_stb, Strings, LOC_DESCRIPTION};
+    use crate::stb::tests::{retriever, write_stb};
+    use crate::talent::{Modifier, Talent};
+    use crate::utils::constants::BASE_LEVEL_DMG;
+
+    fn effect(duration: Duration, actions: Vec<EffAct<ActionName>>) -> Effect {
+        Effect { id: 1, duration, sub_effects: vec![SubEffect { actions, ..Default::default() }], ..Default::default() }
+    }
+
+    #[test]
+    fn render() -> Result<(), TooltipError> {
+        let stats = Stats::default().with(Stat::Power, 1000.0);
+        let weapon = WeaponDamage { min: 100.0, max: 200.0 };
+        let input = TooltipInput { stats: &stats, weapon: Some(&weapon), talents: None };
+        let effects = [effect(Duration::from_secs(6), vec![]), effect(Duration::from_millis(1500), vec![])];
+        let tokens = BTreeMap::from([
+            (1, Token::Duration { effect: 0 }),
+            (2, Token::Duration { effect: 1 }),
+            (3, Token::Value(0.25)),
+            (4, Token::Value(1.0)),
+        ]);
+        let tooltip = input.render(1,
+            "Lasts <<1[%d seconds/%d second/%d seconds]>>, <<2[%d second/%d seconds]>>. <<3>> stacks. <<4[%d stack/%d stacks]>>.",
+            &tokens, &effects,
+        )?;
+        assert_eq!("Lasts 6 seconds, 1.5 seconds. 0.25 stacks. 1 stack.", tooltip.text);
+        assert!(tooltip.problems.is_empty());
+        assert!(matches!(input.render(1, "<<1", &tokens, &effects), Err(TooltipError::Syntax(_))));
+        assert!(matches!(input.render(1, "<<x>>", &tokens, &effects), Err(TooltipError::Syntax(_))));
+
+        // Tokens that can't be filled stay in the text, the rest is still rendered
+        let unknown = input.render(1, "<<5[%d/%d]>> for <<1>>", &tokens, &effects)?;
+        assert_eq!("<<5[%d/%d]>> for 6", unknown.text);
+        assert!(matches!(unknown.problems[..], [TooltipError::UnknownToken(5)]));
+        let no_effect = BTreeMap::from([(1, Token::Duration { effect: 2 })]);
+        assert!(matches!(input.render(1, "<<1>>", &no_effect, &effects)?.problems[..], [TooltipError::NoEffect { token: 1, effect: 2 }]));
+        let no_damage = BTreeMap::from([(1, Token::Damage { effect: 0, action: 0, multiplier: 1.0 })]);
+        assert!(matches!(input.render(1, "<<1>>", &no_damage, &effects)?.problems[..], [TooltipError::NoDamage { .. }]));
+        Ok(())
+    }
+
+    #[test]
+    fn damage() {
+        let stats = Stats::default().with(Stat::Power, 1000.0);
+        let bonus_dmg = stats.bonus_damage();
+        let weapon = WeaponDamage { min: 100.0, max: 200.0 };
+        let damage = SpellDamage { coefficient: 1.0, shp: (0.1, 0.1), amp: -0.5, damage_type: DamageType::Kinetic };
+        let min = 50.0 + bonus_dmg + 0.1 * BASE_LEVEL_DMG;
+        let input = TooltipInput { stats: &stats, weapon: Some(&weapon), talents: None };
+        let range = format!("{}-{}", (min * 2.0).round(), ((min + 50.0) * 2.0).round());
+        assert_eq!((range, ((min + 50.0) * 2.0).round()), input.damage(1, &damage, 2.0));
+        let spell = TooltipInput { stats: &stats, weapon: None, talents: None };
+        assert_eq!(format!("{}", (bonus_dmg + 0.1 * BASE_LEVEL_DMG).round()), spell.damage(1, &damage, 1.0).0);
+        assert_eq!("3", format_number(3.0001));
+        assert_eq!("2.5", format_number(2.5));
+    }
+
+    fn token(token_type: &str, fields: Vec<(&str, FieldValue)>) -> FieldValue {
+        let token_type = variant_value(TokenType::ENUM, token_type).expect("ablDescriptionTokenType variant");
+        let mut class = vec![field(ABL_DESCRIPTION_TOKEN_TYPE, FieldValue::Enum(Id(token_type)))];
+        class.extend(fields.into_iter().map(|(name, value)| field(name, value)));
+        FieldValue::ClassView(ClassView(class))
+    }
+
+    #[test]
+    fn ability_tooltip() -> Result<(), Box<dyn std::error::Error>> {
+        let tokens = LookupList(vec![
+            (FieldValue::Int(Int(1)), token("ablDescriptionTokenTypeDamage", vec![
+                (ABL_DESCRIPTION_TOKEN_EFFECT_INDEX, FieldValue::Int(Int(0))),
+                (ABL_DESCRIPTION_TOKEN_ACTION_INDEX, FieldValue::Int(Int(0))),
+                (ABL_DESCRIPTION_TOKEN_MULTIPLIER, FieldValue::Float(3.0)),
+            ])),
+            (FieldValue::Int(Int(2)), token("ablDescriptionTokenTypeDuration", vec![(ABL_DESCRIPTION_TOKEN_EFFECT_INDEX, FieldValue::Int(Int(0)))])),
+            // A token type that isn't supported, like healing
+            (FieldValue::Int(Int(3)), FieldValue::ClassView(ClassView(vec![field(ABL_DESCRIPTION_TOKEN_TYPE, FieldValue::Enum(Id(99)))]))),
+        ]);
+        let mut ability = node(10, "abl.test.dot", vec![
+            field(ABL_DESCRIPTION_TOKENS, FieldValue::LookupList(tokens)),
+            field(ABL_EFFECT_IDS, FieldValue::List(List(vec![FieldValue::Id(Id(1))]))),
+            retriever(&[(LOC_DESCRIPTION, "str.abl", 1)]),
+        ]);
+        ability.node.base_class = ABILITY_BASE_CLASS.to_string();
+        let mut effect = effect_node(1, &[("SpellDamage", vec![
+            ("Coefficient", FieldValue::Float(0.0)),
+            ("StandardHealthPercentMin", FieldValue::Float(0.5)),
+        ])]);
+        effect.obj.0.push(field(EFF_DURATION, FieldValue::TimeInterval(Duration::from_secs(6))));
+        let mut strings = Strings::default();
+        strings.0.insert("str.abl".to_string(), read_stb(&write_stb(&[(1, "Deals <<1>> damage over <<2[%d seconds/%d second/%d seconds]>>. Heals for <<3>>.")]))?);
+        let data = GameData::from_nodes(vec![ability, effect])?.with_strings(strings);
+
+        let stats = Stats::default();
+        let talents = Talents(vec![Talent { id: 3, fqn: "tal.test".to_string(), modifiers: vec![Modifier::Damage { ability: Some(10), percent: 0.1 }] }]);
+        let input = TooltipInput { stats: &stats, weapon: None, talents: Some(&talents) };
+        let hit = format_number((0.5 * BASE_LEVEL_DMG * 3.0 * 1.1).round());
+        let expected = format!("Deals {hit} damage over 6 seconds. Heals for <<3>>.");
+        let tooltip = input.ability(data.get(10).unwrap(), &data)?;
+        assert_eq!(expected, tooltip.text);
+        assert!(matches!(&tooltip.problems[..], [TooltipError::Field(FieldError::Invalid { field, .. })] if field.starts_with("ablDescriptionTokens[3]")),
+                "{:?}", tooltip.problems);
+
+        // Only abilities get a tooltip, the effect node doesn't
+        let all = input.all(&data);
+        assert_eq!(vec!["abl.test.dot"], all.keys().collect::<Vec<_>>());
+        assert_eq!(expected, all["abl.test.dot"].as_ref().unwrap().text);
+        Ok(())
+    }
+}
@@ -0,0 +1,356 @@
+#![allow(dead_code)]
+
+// Procs. Subeffects with triggers don't run when their effect is applied, they're registered
+// on the entity the effect landed on and run whenever a matching event involves that entity,
+// until the effect's duration runs out.
+
+use std::time::Duration;
+use bevy::ecs::event::ManualEventReader;
+use bevy::prelude::*;
+use crate::action::{apply_sub_effect, param_or, run_effects, DamageDealt, EffectApplied};
+use crate::effect::{Effect, Params, SubEffect, TriggerName};
+use crate::gom::FieldError;
+use crate::rng::{CombatRng, RollKind};
+use crate::timeline::SimTime;
+
+static PARAM_INTERNAL_COOLDOWN: &str = "InternalCooldown";
+static PARAM_REQUIRES_CRITICAL: &str = "RequiresCritical";
+static PARAM_ABILITY: &str = "Ability";
+static PARAM_EFFECT: &str = "Effect";
+static PARAM_CHANCE: &str = "Chance";
+
+#[derive(Event, Debug, Clone, PartialEq)]
+pub struct AbilityActivated {
+    pub caster: Entity,
+    pub target: Entity,
+    pub ability: u64,
+}
+
+#[derive(Event, Debug, Clone, PartialEq)]
+pub struct CombatStarted {
+    pub entity: Entity,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Conditions {
+    pub crit_only: bool,
+    /// Only fire for this ability (OnAbilityActivate) or effect (OnEffectApplied)
+    pub source: Option<u64>,
+    /// Proc chance as a fraction. Below 1 it needs a `CombatRng` to ever fire
+    pub chance: f64,
+}
+
+impl Default for Conditions {
+    fn default() -> Self {
+        Conditions { crit_only: false, source: None, chance: 1.0 }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trigger {
+    pub on: TriggerName,
+    /// Effect the trigger was registered from
+    pub effect: u64,
+    /// Index of `sub_effect` in the effect, so applying the effect again doesn't register it twice
+    pub sub_effect_index: usize,
+    pub conditions: Conditions,
+    pub internal_cooldown: Duration,
+    pub last_fired: Option<Duration>,
+    /// When the effect ends, `None` for effects without a duration like passives
+    pub expires: Option<Duration>,
+    pub sub_effect: SubEffect,
+}
+
+impl Trigger {
+    pub fn ready(&self, now: Duration) -> bool {
+        self.last_fired.map_or(true, |last| now >= last + self.internal_cooldown)
+    }
+
+    pub fn expired(&self, now: Duration) -> bool {
+        self.expires.is_some_and(|expires| now >= expires)
+    }
+}
+
+/// Triggers registered on an entity
+#[derive(Component, Debug, Default, PartialEq)]
+pub struct Triggers(pub Vec<Trigger>);
+
+/// Something that happened to `owner`, which can fire its triggers
+#[derive(Debug, Clone, PartialEq)]
+struct Activation {
+    on: TriggerName,
+    owner: Entity,
+    /// Target of the fired subeffect
+    other: Entity,
+    source: Option<u64>,
+    crit: bool,
+}
+
+impl Conditions {
+    fn from_params(on: &TriggerName, params: &Params) -> Result<Self, FieldError> {
+        let source = match on {
+            TriggerName::OnAbilityActivate => param_or(params, PARAM_ABILITY, Params::id, 0)?,
+            TriggerName::OnEffectApplied => param_or(params, PARAM_EFFECT, Params::id, 0)?,
+            _ => 0,
+        };
+        Ok(Conditions {
+            crit_only: param_or(params, PARAM_REQUIRES_CRITICAL, Params::bool, false)?,
+            source: (source != 0).then_some(source),
+            chance: f64::from(param_or(params, PARAM_CHANCE, Params::float, 1.0)?),
+        })
+    }
+
+    fn allow(&self, activation: &Activation) -> bool {
+        (!self.crit_only || activation.crit) && self.source.map_or(true, |source| activation.source == Some(source))
+    }
+}
+
+/// Triggers of every triggered subeffect of `effect` applied at `now`. Triggers with bad parameters are skipped and returned
+pub fn triggers_of(effect: &Effect, now: Duration) -> (Vec<Trigger>, Vec<FieldError>) {
+    let expires = (!effect.duration.is_zero()).then(|| now + effect.duration);
+    let mut triggers = vec![];
+    let mut problems = vec![];
+    for (i, sub_effect) in effect.sub_effects.iter().enumerate() {
+        for trigger in &sub_effect.triggers {
+            let params = &trigger.params;
+            let parsed = Conditions::from_params(&trigger.name, params).and_then(|conditions| {
+                Ok(Trigger {
+                    on: trigger.name.clone(),
+                    effect: effect.id,
+                    sub_effect_index: i,
+                    conditions,
+                    internal_cooldown: param_or(params, PARAM_INTERNAL_COOLDOWN, Params::time_interval, Duration::ZERO)?,
+                    last_fired: None,
+                    expires,
+                    sub_effect: sub_effect.clone(),
+                })
+            });
+            match parsed {
+                Ok(t) => triggers.push(t),
+                Err(e) => problems.push(e.within(&format!("effSubEffects[{i}].effTriggers"))),
+            }
+        }
+    }
+    (triggers, problems)
+}
+
+/// Registers the triggers of `effect` on `entity`. Ones that already are get their duration refreshed
+pub fn register(world: &mut World, entity: Entity, effect: &Effect) -> Vec<FieldError> {
+    let now = world.get_resource::<SimTime>().copied().unwrap_or_default().0;
+    let (triggers, problems) = triggers_of(effect, now);
+    if triggers.is_empty() {
+        return problems;
+    }
+    let Some(mut entity_mut) = world.get_entity_mut(entity) else {
+        return problems;
+    };
+    if !entity_mut.contains::<Triggers>() {
+        entity_mut.insert(Triggers::default());
+    }
+    let mut registered = entity_mut.get_mut::<Triggers>().expect("was just inserted");
+    for trigger in triggers {
+        let known = registered.0.iter_mut().find(|t| {
+            t.effect == trigger.effect && t.sub_effect_index == trigger.sub_effect_index && t.on == trigger.on
+        });
+        match known {
+            Some(known) => known.expires = trigger.expires,
+            None => registered.0.push(trigger),
+        }
+    }
+    problems
+}
+
+/// Drops the triggers of effects that ended
+fn expire(world: &mut World) {
+    let now = world.get_resource::<SimTime>().copied().unwrap_or_default().0;
+    let mut query = world.query::<&mut Triggers>();
+    for mut triggers in query.iter_mut(world) {
+        if triggers.0.iter().any(|t| t.expired(now)) {
+            triggers.0.retain(|t| !t.expired(now));
+        }
+    }
+}
+
+fn fire(world: &mut World, activation: &Activation) {
+    let now = world.get_resource::<SimTime>().copied().unwrap_or_default().0;
+    let Some(triggers) = world.get::<Triggers>(activation.owner) else {
+        return;
+    };
+    let candidates: Vec<_> = triggers.0.iter()
+        .enumerate()
+        .filter(|(_, t)| t.on == activation.on && t.ready(now) && t.conditions.allow(activation))
+        .map(|(i, t)| (i, t.conditions.chance))
+        .collect();
+    let mut rng = world.get_resource_mut::<CombatRng>();
+    let procs: Vec<_> = candidates.into_iter()
+        .filter(|&(_, chance)| chance >= 1.0 || rng.as_mut().is_some_and(|rng| rng.roll(RollKind::Proc, activation.owner, chance)))
+        .map(|(i, _)| i)
+        .collect();
+
+    let mut triggers = world.get_mut::<Triggers>(activation.owner).expect("was just read");
+    let mut fired = vec![];
+    for i in procs {
+        let trigger = &mut triggers.0[i];
+        trigger.last_fired = Some(now);
+        fired.push((trigger.effect, trigger.sub_effect.clone()));
+    }
+    for (effect, sub_effect) in fired {
+        for problem in apply_sub_effect(world, activation.owner, activation.other, effect, &sub_effect) {
+            warn!("triggered effect {effect}: {problem}");
+        }
+    }
+}
+
+pub fn fire_triggers(
+    world: &mut World,
+    mut damage: Local<ManualEventReader<DamageDealt>>,
+    mut applied: Local<ManualEventReader<EffectApplied>>,
+    mut activated: Local<ManualEventReader<AbilityActivated>>,
+    mut combat: Local<ManualEventReader<CombatStarted>>,
+) {
+    let mut activations = vec![];
+    let mut effects = vec![];
+    for e in damage.read(world.resource::<Events<DamageDealt>>()) {
+        activations.push(Activation { on: TriggerName::OnDamageDealt, owner: e.caster, other: e.target, source: None, crit: e.crit });
+    }
+    for e in applied.read(world.resource::<Events<EffectApplied>>()) {
+        activations.push(Activation { on: TriggerName::OnEffectApplied, owner: e.caster, other: e.target, source: Some(e.effect.id), crit: false });
+        effects.push((e.target, e.effect.clone()));
+    }
+    for e in activated.read(world.resource::<Events<AbilityActivated>>()) {
+        activations.push(Activation { on: TriggerName::OnAbilityActivate, owner: e.caster, other: e.target, source: Some(e.ability), crit: false });
+    }
+    for e in combat.read(world.resource::<Events<CombatStarted>>()) {
+        activations.push(Activation { on: TriggerName::OnEnterCombat, owner: e.entity, other: e.entity, source: None, crit: false });
+    }
+
+    expire(world);
+    for (target, effect) in effects {
+        for problem in register(world, target, &effect) {
+            warn!("effect {}: {problem}", effect.id);
+        }
+    }
+    for activation in activations {
+        fire(world, &activation);
+    }
+}
+
+pub struct TriggerPlugin;
+
+impl Plugin for TriggerPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<AbilityActivated>()
+           .add_event::<CombatStarted>()
+           .add_systems(Update, fire_triggers.after(run_effects));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trigger(on: TriggerName, conditions: Conditions, internal_cooldown: Duration) -> Trigger {
+        Trigger {
+            on,
+            effect: 1,
+            sub_effect_index: 0,
+            conditions,
+            internal_cooldown,
+            last_fired: None,
+            expires: None,
+            sub_effect: SubEffect::default(),
+        }
+    }
+
+    #[test]
+    fn cooldowns_and_conditions() {
+        let mut world = World::new();
+        world.init_resource::<SimTime>();
+        world.init_resource::<Events<DamageDealt>>();
+        world.init_resource::<Events<EffectApplied>>();
+        world.init_resource::<Events<AbilityActivated>>();
+        world.init_resource::<Events<CombatStarted>>();
+        let target = world.spawn_empty().id();
+        let owner = world.spawn(Triggers(vec![
+            trigger(TriggerName::OnDamageDealt, Conditions::default(), Duration::from_secs(10)),
+            trigger(TriggerName::OnDamageDealt, Conditions { crit_only: true, ..Default::default() }, Duration::ZERO),
+            trigger(TriggerName::OnAbilityActivate, Conditions { source: Some(7), ..Default::default() }, Duration::ZERO),
+            trigger(TriggerName::OnAbilityActivate, Conditions { chance: 0.5, ..Default::default() }, Duration::ZERO),
+        ])).id();
+        let mut schedule = Schedule::default();
+        schedule.add_systems(fire_triggers);
+        let mut step = |world: &mut World, secs: u64, crit: bool, ability: u64| {
+            world.resource_mut::<SimTime>().0 = Duration::from_secs(secs);
+            world.send_event(DamageDealt { caster: owner, target, source: 1, amount: 1.0, crit });
+            world.send_event(AbilityActivated { caster: owner, target, ability });
+            schedule.run(world);
+            world.get::<Triggers>(owner).unwrap().0.iter().map(|t| t.last_fired.map(|d| d.as_secs())).collect::<Vec<_>>()
+        };
+        assert_eq!(vec![Some(0), None, None, None], step(&mut world, 0, false, 3));
+        assert_eq!(vec![Some(0), Some(5), Some(5), None], step(&mut world, 5, true, 7));
+        assert_eq!(vec![Some(10), Some(5), Some(5), None], step(&mut world, 10, false, 3));
+    }
+
+    #[test]
+    fn expiry() {
+        use crate::effect::{EffAct, EffectSlot};
+
+        let mut world = World::new();
+        world.init_resource::<SimTime>();
+        world.init_resource::<Events<DamageDealt>>();
+        world.init_resource::<Events<EffectApplied>>();
+        world.init_resource::<Events<AbilityActivated>>();
+        world.init_resource::<Events<CombatStarted>>();
+        let owner = world.spawn_empty().id();
+        let effect = Effect {
+            id: 2,
+            slot: EffectSlot::Buff,
+            duration: Duration::from_secs(5),
+            sub_effects: vec![SubEffect {
+                triggers: vec![EffAct { name: TriggerName::OnDamageDealt, params: Params::default(), function_tags: vec![], tag_exclusions: vec![] }],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let passive = Effect { id: 3, duration: Duration::ZERO, ..effect.clone() };
+        let mut schedule = Schedule::default();
+        schedule.add_systems(fire_triggers);
+        let mut step = |world: &mut World, secs: u64, applied: &[&Effect]| {
+            world.resource_mut::<SimTime>().0 = Duration::from_secs(secs);
+            for &effect in applied {
+                world.send_event(EffectApplied { caster: owner, target: owner, effect: effect.clone() });
+            }
+            world.send_event(DamageDealt { caster: owner, target: owner, source: 1, amount: 1.0, crit: false });
+            schedule.run(world);
+            world.get::<Triggers>(owner).map_or(vec![], |triggers| {
+                triggers.0.iter().map(|t| (t.effect, t.last_fired.map(|d| d.as_secs()))).collect()
+            })
+        };
+        assert_eq!(vec![(2, Some(0)), (3, Some(0))], step(&mut world, 0, &[&effect, &passive]));
+        assert_eq!(vec![(2, Some(4)), (3, Some(4))], step(&mut world, 4, &[]));
+        // Gone once the effect ends, unless it was applied again in the meantime
+        assert_eq!(vec![(3, Some(5))], step(&mut world, 5, &[]));
+        assert_eq!(vec![(3, Some(6)), (2, Some(6))], step(&mut world, 6, &[&effect]));
+        assert_eq!(vec![(3, Some(10)), (2, Some(10))], step(&mut world, 10, &[&effect]));
+        assert_eq!(vec![(3, Some(14)), (2, Some(14))], step(&mut world, 14, &[]));
+        assert_eq!(vec![(3, Some(100))], step(&mut world, 100, &[]));
+    }
+
+    #[test]
+    fn proc_chance() {
+        let mut world = World::new();
+        world.init_resource::<Events<DamageDealt>>();
+        world.insert_resource(CombatRng::new(1));
+        let owner = world.spawn(Triggers(vec![
+            trigger(TriggerName::OnDamageDealt, Conditions { chance: 0.5, ..Default::default() }, Duration::ZERO),
+        ])).id();
+        let activation = Activation { on: TriggerName::OnDamageDealt, owner, other: owner, source: None, crit: false };
+        let mut procs = 0;
+        for secs in 0..1000 {
+            world.insert_resource(SimTime(Duration::from_secs(secs)));
+            fire(&mut world, &activation);
+            procs += usize::from(world.get::<Triggers>(owner).unwrap().0[0].last_fired == Some(Duration::from_secs(secs)));
+        }
+        assert!((400..600).contains(&procs), "{procs}");
+    }
+}
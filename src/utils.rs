@@ -12,8 +12,15 @@ pub mod constants {
     // static POWER_HEAL_BONUS: f64 = 0.17; // From dulfy crit chance guide, need to double-check
 
 
+    /// Global cooldown in seconds, before alacrity
+    pub static GCD: f64 = 1.5;
+    /// Seconds between channel ticks, before alacrity. Channels tick when they start too
+    pub static CHANNEL_TICK: f64 = 1.0;
+
     pub static BASE_CRIT: f64 = 0.5;
-    pub static CRIT_MAGIC: f64 = 2.41;
+    /// Accuracy of every attack before ratings, accuracy over 100% cancels out defense
+    pub static BASE_ACCURACY: f64 = 1.0;
+    pub static BASE_CRIT_CHANCE: f64 = 0.05;
 
     // Class buffs
     // Works for m/r and f/t and heals. Duplicate for heals or make common variable?
@@ -27,6 +34,8 @@ pub mod constants {
     // Same for acc, healing recieved, hp, crit chance
 
     pub static BOSS_ARMOR: f64 = 17227.0;
+    /// Operation bosses avoid 10% of attacks, hence the usual 110% accuracy
+    pub static BOSS_DEFENSE: f64 = 0.1;
     pub static ARMOR_MAGIC_1: f64 = 389.99;
     pub static ARMOR_MAGIC_2: f64 = 800.0;
 
@@ -34,9 +43,23 @@ pub mod constants {
     pub static COEFFICIENT: f64 = 0.72;
     pub static SHP: f64 = 0.072; // StandardHealthPercent, should be min/max
     pub static AMP: f64 = -0.52; // AmountModifierPercent
-    pub static PASSIVE_MODS: f64 = -0.05; // Dark Charge (-0.1) + Trashing Blades (0.05)
 
     // From dummy armor reduction
     pub static MELEE_DMG_BUFF: f64 = 0.05;
     pub static ARMOR_DEBUFF: f64 = 0.2;
 }
+
+pub mod formulas {
+    use super::constants::{ARMOR_MAGIC_1, ARMOR_MAGIC_2, BASE_LEVEL_DMG, LEVEL};
+
+    /// Fraction of damage `armor` mitigates
+    pub fn armor_reduction(armor: f64) -> f64 {
+        armor / (armor + ARMOR_MAGIC_1 * LEVEL + ARMOR_MAGIC_2)
+    }
+
+    /// Damage of one hit as shown on the tooltip, before passives and mitigation.
+    /// `amp` (AmountModifierPercent) only scales weapon damage, spells have no weapon part.
+    pub fn tooltip_damage(weapon_dmg: f64, amp: f64, coefficient: f64, bonus_dmg: f64, shp: f64) -> f64 {
+        weapon_dmg * (1.0 + amp) + coefficient * bonus_dmg + shp * BASE_LEVEL_DMG
+    }
+}